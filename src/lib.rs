@@ -1,5 +1,10 @@
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::ops::{Index, IndexMut};
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use web_sys::HtmlCanvasElement;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement};
 
 // Import the `console.log` function from the browser console
 #[wasm_bindgen]
@@ -10,11 +15,59 @@ extern "C" {
 
 // Define a macro to make logging easier
 macro_rules! console_log {
-    ($($t:tt)*) => (log(&format_args!($($t)*).to_string()))
+    ($($t:tt)*) => (log_event(&format_args!($($t)*).to_string()))
+}
+
+// Maximum number of events kept in the event log before the oldest are dropped
+const MAX_LOG_EVENTS: usize = 256;
+
+// Event log shared by every game instance (WASM runs single-threaded). Each event carries
+// a sequence number that keeps increasing even as old events are dropped or cleared.
+thread_local! {
+    static EVENT_LOG: RefCell<VecDeque<(u64, String)>> = const { RefCell::new(VecDeque::new()) };
+    static EVENT_SEQUENCE: Cell<u64> = const { Cell::new(0) };
+    // Console output defaults to on in debug builds and off in release builds
+    static VERBOSE: Cell<bool> = const { Cell::new(cfg!(debug_assertions)) };
+}
+
+// Record an event in the ring buffer and mirror it to the browser console if enabled
+fn log_event(message: &str) {
+    EVENT_LOG.with(|events| {
+        let mut events = events.borrow_mut();
+        if events.len() >= MAX_LOG_EVENTS {
+            events.pop_front();
+        }
+        let sequence = EVENT_SEQUENCE.with(|sequence| {
+            sequence.set(sequence.get() + 1);
+            sequence.get()
+        });
+        events.push_back((sequence, message.to_string()));
+    });
+
+    if VERBOSE.with(|verbose| verbose.get()) {
+        log(message);
+    }
 }
 
 // Go game constants
 const MAX_BOARD_SIZE: usize = 19; // Maximum supported board size
+const COLUMN_LETTERS: &[u8; MAX_BOARD_SIZE] = b"ABCDEFGHJKLMNOPQRST"; // Conventional column labels (no "I")
+
+// Zobrist keys for [black, white] stones on each point, generated at compile time with splitmix64
+const ZOBRIST_KEYS: [[u64; 2]; MAX_BOARD_SIZE * MAX_BOARD_SIZE] = {
+    let mut keys = [[0u64; 2]; MAX_BOARD_SIZE * MAX_BOARD_SIZE];
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut i = 0;
+    while i < MAX_BOARD_SIZE * MAX_BOARD_SIZE * 2 {
+        state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        keys[i / 2][i % 2] = z ^ (z >> 31);
+        i += 1;
+    }
+    keys
+};
 
 // Game state
 #[derive(Clone, Copy, PartialEq, Debug)]
@@ -24,712 +77,5741 @@ pub enum StoneState {
     White,
 }
 
-// Move representation for sequence encoding
-#[derive(Clone, Debug)]
-struct Move {
-    x: Option<usize>, // None for pass moves
-    y: Option<usize>, // None for pass moves
-    player: StoneState,
+// Orthogonal on-board neighbors of one point, in left, right, up, down order
+#[derive(Clone, Copy, Default)]
+struct Neighbors {
+    points: [(usize, usize); 4],
+    len: usize,
 }
 
-// Simple Go game struct without WebGPU for now
-#[wasm_bindgen]
-pub struct GoGame {
-    board: [[StoneState; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
-    move_numbers: [[u32; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], // Track move number for each position (0 = no move)
-    board_size: usize,
-    current_player: StoneState,
-    canvas_width: u32,
-    canvas_height: u32,
-    move_sequence: Vec<Move>, // Chronological sequence of moves - replaces history
-    move_index: usize, // Current position in move sequence (for undo/redo)
-    black_captures: u32,
-    white_captures: u32,
-    last_move: Option<(usize, usize)>, // Track the last move position
+impl Neighbors {
+    fn as_slice(&self) -> &[(usize, usize)] {
+        &self.points[..self.len]
+    }
 }
 
-#[wasm_bindgen]
-impl GoGame {
-    #[wasm_bindgen(constructor)]
-    pub fn new(canvas: HtmlCanvasElement) -> GoGame {
-        Self::new_with_size(canvas, 19)
+// Neighbors of every point for one board size, indexed by y * MAX_BOARD_SIZE + x
+fn neighbor_table(board_size: usize) -> Rc<[Neighbors]> {
+    let mut table = vec![Neighbors::default(); MAX_BOARD_SIZE * MAX_BOARD_SIZE];
+    for y in 0..board_size {
+        for x in 0..board_size {
+            let entry = &mut table[y * MAX_BOARD_SIZE + x];
+            let adjacent_positions = [
+                (x.wrapping_sub(1), y), // Left
+                (x + 1, y),             // Right
+                (x, y.wrapping_sub(1)), // Up
+                (x, y + 1),             // Down
+            ];
+            for (adj_x, adj_y) in adjacent_positions {
+                if adj_x < board_size && adj_y < board_size {
+                    entry.points[entry.len] = (adj_x, adj_y);
+                    entry.len += 1;
+                }
+            }
+        }
     }
+    table.into()
+}
 
-    pub fn new_with_size(canvas: HtmlCanvasElement, board_size: usize) -> GoGame {
-        console_log!("Initializing Go game with {}x{} board...", board_size, board_size);
+// Small seeded xorshift64* generator, so random choices can be reproduced from a seed
+#[derive(Clone)]
+struct Rng(u64);
 
-        // Initialize logging
-        console_error_panic_hook::set_once();
+impl Rng {
+    fn new(seed: u32) -> Rng {
+        Rng(seed as u64 ^ 0x9E37_79B9_7F4A_7C15) // Never zero, which would stick
+    }
 
-        let valid_size = match board_size {
-            9 | 13 | 19 => board_size,
-            _ => {
-                console_log!("Invalid board size {}, defaulting to 19x19", board_size);
-                19
+    // A number in 0..bound
+    fn below(&mut self, bound: usize) -> usize {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        (self.0.wrapping_mul(0x2545_F491_4F6C_DD1D) >> 33) as usize % bound
+    }
+
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+}
+
+// Reusable flood-fill buffers. A point counts as visited when its mark equals the current
+// generation, so starting a new fill bumps the generation instead of clearing the array.
+#[derive(Clone)]
+struct FloodScratch {
+    marks: [u32; MAX_BOARD_SIZE * MAX_BOARD_SIZE],
+    generation: u32,
+    stack: Vec<(usize, usize)>,
+}
+
+impl FloodScratch {
+    fn new() -> Self {
+        FloodScratch {
+            marks: [0; MAX_BOARD_SIZE * MAX_BOARD_SIZE],
+            generation: 0,
+            stack: Vec::with_capacity(MAX_BOARD_SIZE * MAX_BOARD_SIZE),
+        }
+    }
+
+    // Start a new fill with nothing visited and an empty stack
+    fn reset(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            self.marks = [0; MAX_BOARD_SIZE * MAX_BOARD_SIZE];
+            self.generation = 1;
+        }
+        self.stack.clear();
+    }
+
+    // Mark (x, y) visited; false if it already was
+    fn visit(&mut self, x: usize, y: usize) -> bool {
+        let mark = &mut self.marks[y * MAX_BOARD_SIZE + x];
+        if *mark == self.generation {
+            return false;
+        }
+        *mark = self.generation;
+        true
+    }
+
+    fn is_visited(&self, x: usize, y: usize) -> bool {
+        self.marks[y * MAX_BOARD_SIZE + x] == self.generation
+    }
+}
+
+// A connected region of empty points and the stone colors bordering it
+struct EmptyRegion {
+    points: Vec<(usize, usize)>,
+    borders_black: bool,
+    borders_white: bool,
+}
+
+// Core rules for a single position: the stones, the pending ko, legality and captures,
+// independent of move history, rendering and JS types. GoGame wraps one and adapts it to
+// wasm; native code (tests, a server) can use it directly. Rows index as board[y][x].
+#[derive(Clone)]
+pub struct Board {
+    size: usize,
+    stones: [[StoneState; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+    ko: Option<(usize, usize, StoneState)>, // Ko point and the color forbidden from playing there
+    neighbor_table: Rc<[Neighbors]>, // Neighbors of each point for the current size
+    flood: RefCell<FloodScratch>, // Scratch buffers shared by the flood fills (never held across calls)
+}
+
+impl Index<usize> for Board {
+    type Output = [StoneState; MAX_BOARD_SIZE];
+
+    fn index(&self, y: usize) -> &Self::Output {
+        &self.stones[y]
+    }
+}
+
+impl IndexMut<usize> for Board {
+    fn index_mut(&mut self, y: usize) -> &mut Self::Output {
+        &mut self.stones[y]
+    }
+}
+
+impl Board {
+    // An empty board of the given size (not checked against the supported sizes)
+    pub fn new(size: usize) -> Board {
+        Board {
+            size,
+            stones: [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+            ko: None,
+            neighbor_table: neighbor_table(size),
+            flood: RefCell::new(FloodScratch::new()),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    // Change the size, leaving the stones for the caller to reset
+    fn set_size(&mut self, size: usize) {
+        self.size = size;
+        self.neighbor_table = neighbor_table(size);
+    }
+
+    // The stone at (x, y); Empty off the board
+    pub fn stone(&self, x: usize, y: usize) -> StoneState {
+        if x >= self.size || y >= self.size {
+            return StoneState::Empty;
+        }
+        self.stones[y][x]
+    }
+
+    // The pending ko point and the color forbidden from playing there
+    pub fn ko(&self) -> Option<(usize, usize, StoneState)> {
+        self.ko
+    }
+
+    // Whether `color` may play at (x, y) under the basic rules: on the board, empty, not
+    // retaking a ko and not suicide
+    pub fn is_legal(&self, x: usize, y: usize, color: StoneState) -> Result<(), MoveResult> {
+        self.check_placement(x, y, color, true)
+    }
+
+    // is_legal with the simple ko check optional (for games played without a ko rule)
+    fn check_placement(&self, x: usize, y: usize, color: StoneState, simple_ko: bool) -> Result<(), MoveResult> {
+        if x >= self.size || y >= self.size {
+            return Err(MoveResult::OutOfBounds);
+        }
+        if color == StoneState::Empty {
+            return Err(MoveResult::InvalidColor);
+        }
+        if self.stones[y][x] != StoneState::Empty {
+            return Err(MoveResult::Occupied);
+        }
+        if simple_ko && self.ko == Some((x, y, color)) {
+            return Err(MoveResult::Ko);
+        }
+        if self.is_suicidal_move(x, y, color) {
+            return Err(MoveResult::Suicide);
+        }
+        Ok(())
+    }
+
+    // check_placement for every point at once, in row-major order. One flood fill per group
+    // counts its liberties; each empty point is then decided from its neighbors alone.
+    fn check_all_placements(&self, color: StoneState, simple_ko: bool) -> Vec<Result<(), MoveResult>> {
+        let size = self.size;
+        let opponent = opponent_of(color);
+
+        // Liberty count of the group through each stone (0 for empty points); liberty_marks
+        // holds the id of the last group that counted each empty point
+        let mut liberties = vec![0usize; size * size];
+        let mut liberty_marks = vec![0usize; size * size];
+        let mut group = Vec::new();
+        let mut flood = self.flood.borrow_mut();
+        flood.reset();
+        for start_y in 0..size {
+            for start_x in 0..size {
+                let stone = self.stones[start_y][start_x];
+                if stone == StoneState::Empty || !flood.visit(start_x, start_y) {
+                    continue;
+                }
+
+                let id = start_y * size + start_x + 1;
+                let mut count = 0;
+                group.clear();
+                flood.stack.push((start_x, start_y));
+                while let Some((x, y)) = flood.stack.pop() {
+                    group.push((x, y));
+                    for &(adj_x, adj_y) in self.neighbors(x, y) {
+                        let adj = self.stones[adj_y][adj_x];
+                        if adj == StoneState::Empty {
+                            if liberty_marks[adj_y * size + adj_x] != id {
+                                liberty_marks[adj_y * size + adj_x] = id;
+                                count += 1;
+                            }
+                        } else if adj == stone && flood.visit(adj_x, adj_y) {
+                            flood.stack.push((adj_x, adj_y));
+                        }
+                    }
+                }
+                for &(x, y) in &group {
+                    liberties[y * size + x] = count;
+                }
+            }
+        }
+        drop(flood);
+
+        let mut placements = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                let placement = if color == StoneState::Empty {
+                    Err(MoveResult::InvalidColor)
+                } else if self.stones[y][x] != StoneState::Empty {
+                    Err(MoveResult::Occupied)
+                } else if simple_ko && self.ko == Some((x, y, color)) {
+                    Err(MoveResult::Ko)
+                } else {
+                    // Not suicide if the stone has a liberty, joins a friendly group with
+                    // another liberty, or takes the last liberty of an enemy group
+                    let lives = self.neighbors(x, y).iter().any(|&(adj_x, adj_y)| {
+                        let adj = self.stones[adj_y][adj_x];
+                        let adj_liberties = liberties[adj_y * size + adj_x];
+                        adj == StoneState::Empty || (adj == color && adj_liberties > 1) || (adj == opponent && adj_liberties == 1)
+                    });
+                    if lives { Ok(()) } else { Err(MoveResult::Suicide) }
+                };
+                placements.push(placement);
+            }
+        }
+        placements
+    }
+
+    // Play a legal stone, returning the stones it captured
+    pub fn place(&mut self, x: usize, y: usize, color: StoneState) -> Result<Vec<(usize, usize)>, MoveResult> {
+        self.is_legal(x, y, color)?;
+        Ok(self.play(x, y, color))
+    }
+
+    // Put a stone down without checking legality, remove the opponent groups it leaves
+    // without liberties and update the ko point; returns the captured stones
+    fn play(&mut self, x: usize, y: usize, color: StoneState) -> Vec<(usize, usize)> {
+        self.stones[y][x] = color;
+        let opponent = opponent_of(color);
+
+        // Check all adjacent positions for opponent groups to capture
+        let mut captured_stones = Vec::new();
+        let neighbors = self.neighbor_table[y * MAX_BOARD_SIZE + x];
+        for &(adj_x, adj_y) in neighbors.as_slice() {
+            if self.stones[adj_y][adj_x] == opponent {
+                captured_stones.extend(self.capture(adj_x, adj_y));
+            }
+        }
+
+        // A single stone capturing a single stone and left with that point as its only
+        // liberty creates a ko: the opponent may not retake immediately
+        self.ko = None;
+        if let [(cap_x, cap_y)] = captured_stones[..] {
+            let mut group = Vec::new();
+            self.find_group_stones(x, y, color, &mut group);
+            if group.len() == 1 && self.group_liberties(x, y) == [(cap_x, cap_y)] {
+                self.ko = Some((cap_x, cap_y, opponent));
+            }
+        }
+
+        captured_stones
+    }
+
+    // Remove the group at (x, y) if it has no liberties, returning the removed stones
+    pub fn capture(&mut self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let color = self.stone(x, y);
+        if color == StoneState::Empty || self.has_liberties_on_board(&self.stones, x, y, color) {
+            return Vec::new();
+        }
+
+        let mut to_capture = Vec::new();
+        self.find_group_stones(x, y, color, &mut to_capture);
+        for &(cap_x, cap_y) in &to_capture {
+            self.stones[cap_y][cap_x] = StoneState::Empty;
+        }
+        to_capture
+    }
+
+    // Area score: Black's stones plus empty regions bordered only by Black, minus the same
+    // for White, minus komi. Every stone counts as alive.
+    pub fn score(&self, komi: f32) -> f32 {
+        let mut black_area = 0;
+        let mut white_area = 0;
+        for y in 0..self.size {
+            for x in 0..self.size {
+                match self.stones[y][x] {
+                    StoneState::Black => black_area += 1,
+                    StoneState::White => white_area += 1,
+                    StoneState::Empty => {}
+                }
+            }
+        }
+        for region in self.empty_regions() {
+            match (region.borders_black, region.borders_white) {
+                (true, false) => black_area += region.points.len(),
+                (false, true) => white_area += region.points.len(),
+                _ => {}
+            }
+        }
+
+        black_area as f32 - white_area as f32 - komi
+    }
+
+    // Find all stones in a connected group of the same color, appending them to `group` in
+    // depth-first order (stones already in `group` are skipped)
+    fn find_group_stones(&self, x: usize, y: usize, color: StoneState, group: &mut Vec<(usize, usize)>) {
+        if x >= self.size || y >= self.size || self.stones[y][x] != color {
+            return;
+        }
+
+        let mut flood = self.flood.borrow_mut();
+        flood.reset();
+        for &(gx, gy) in group.iter() {
+            flood.visit(gx, gy);
+        }
+
+        flood.stack.push((x, y));
+        while let Some((cx, cy)) = flood.stack.pop() {
+            if !flood.visit(cx, cy) {
+                continue;
+            }
+            group.push((cx, cy));
+            // Pushed in reverse so the left neighbor is explored first
+            for &(adj_x, adj_y) in self.neighbors(cx, cy).iter().rev() {
+                if self.stones[adj_y][adj_x] == color && !flood.is_visited(adj_x, adj_y) {
+                    flood.stack.push((adj_x, adj_y));
+                }
+            }
+        }
+    }
+
+    // Neighbors of (x, y) from the precomputed table
+    fn neighbors(&self, x: usize, y: usize) -> &[(usize, usize)] {
+        self.neighbor_table[y * MAX_BOARD_SIZE + x].as_slice()
+    }
+
+    // Find the distinct liberties (adjacent empty points) of the group containing (x, y)
+    fn group_liberties(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let color = self.stones[y][x];
+        let mut group = Vec::new();
+        self.find_group_stones(x, y, color, &mut group);
+
+        let mut flood = self.flood.borrow_mut();
+        flood.reset();
+        let mut liberties = Vec::new();
+        for &(gx, gy) in &group {
+            for &(adj_x, adj_y) in self.neighbors(gx, gy) {
+                if self.stones[adj_y][adj_x] == StoneState::Empty && flood.visit(adj_x, adj_y) {
+                    liberties.push((adj_x, adj_y));
+                }
+            }
+        }
+
+        liberties
+    }
+
+    // Flood-fill every group once, in row-major order of each group's first stone
+    fn group_summaries(&self) -> Vec<GroupSummary> {
+        // liberty_marks holds the id of the last group that counted each empty point
+        let mut liberty_marks = [0usize; MAX_BOARD_SIZE * MAX_BOARD_SIZE];
+        let mut groups = Vec::new();
+        let mut flood = self.flood.borrow_mut();
+        flood.reset();
+
+        for start_y in 0..self.size {
+            for start_x in 0..self.size {
+                let color = self.stones[start_y][start_x];
+                if color == StoneState::Empty || !flood.visit(start_x, start_y) {
+                    continue;
+                }
+
+                let id = groups.len() + 1;
+                let mut stones = 0;
+                let mut liberties = 0;
+                flood.stack.push((start_x, start_y));
+                while let Some((x, y)) = flood.stack.pop() {
+                    stones += 1;
+                    for &(adj_x, adj_y) in self.neighbors(x, y) {
+                        let adjacent = self.stones[adj_y][adj_x];
+                        if adjacent == StoneState::Empty {
+                            let mark = &mut liberty_marks[adj_y * MAX_BOARD_SIZE + adj_x];
+                            if *mark != id {
+                                *mark = id;
+                                liberties += 1;
+                            }
+                        } else if adjacent == color && flood.visit(adj_x, adj_y) {
+                            flood.stack.push((adj_x, adj_y));
+                        }
+                    }
+                }
+
+                groups.push(GroupSummary {
+                    color,
+                    stones,
+                    liberties,
+                    x: start_x,
+                    y: start_y,
+                });
+            }
+        }
+
+        groups
+    }
+
+    // Flood-fill every connected empty region, in row-major order of each region's first point
+    fn empty_regions(&self) -> Vec<EmptyRegion> {
+        let mut flood = self.flood.borrow_mut();
+        flood.reset();
+        let mut regions = Vec::new();
+
+        for start_y in 0..self.size {
+            for start_x in 0..self.size {
+                if self.stones[start_y][start_x] != StoneState::Empty || !flood.visit(start_x, start_y) {
+                    continue;
+                }
+
+                let mut region = EmptyRegion {
+                    points: Vec::new(),
+                    borders_black: false,
+                    borders_white: false,
+                };
+                flood.stack.push((start_x, start_y));
+
+                while let Some((x, y)) = flood.stack.pop() {
+                    region.points.push((x, y));
+
+                    for &(adj_x, adj_y) in self.neighbors(x, y) {
+                        match self.stones[adj_y][adj_x] {
+                            StoneState::Empty => {
+                                if flood.visit(adj_x, adj_y) {
+                                    flood.stack.push((adj_x, adj_y));
+                                }
+                            }
+                            StoneState::Black => region.borders_black = true,
+                            StoneState::White => region.borders_white = true,
+                        }
+                    }
+                }
+
+                region.points.sort_by_key(|&(x, y)| (y, x));
+                regions.push(region);
             }
+        }
+
+        regions
+    }
+
+    // Check if placing a stone would be suicidal (violate suicide rule)
+    fn is_suicidal_move(&self, x: usize, y: usize, color: StoneState) -> bool {
+        // A stone with an empty neighbor always has a liberty
+        if self.neighbors(x, y).iter().any(|&(adj_x, adj_y)| self.stones[adj_y][adj_x] == StoneState::Empty) {
+            return false;
+        }
+
+        // Temporarily place the stone to test
+        let mut test_board = self.stones;
+        test_board[y][x] = color;
+
+        let opponent = match color {
+            StoneState::Black => StoneState::White,
+            StoneState::White => StoneState::Black,
+            StoneState::Empty => return false,
         };
 
-        let initial_board = [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
-        let initial_move_numbers = [[0u32; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        // First check if this move would capture any opponent groups
+        // If it captures opponents, it's not suicidal even if it has no liberties
+        for &(adj_x, adj_y) in self.neighbors(x, y) {
+            if test_board[adj_y][adj_x] == opponent && !self.has_liberties_on_board(&test_board, adj_x, adj_y, opponent) {
+                // This move would capture opponent stones, so it's not suicidal
+                return false;
+            }
+        }
 
-        GoGame {
-            board: initial_board,
-            move_numbers: initial_move_numbers,
-            board_size: valid_size,
-            current_player: StoneState::Black,
-            canvas_width: canvas.width(),
-            canvas_height: canvas.height(),
-            move_sequence: Vec::new(),
-            move_index: 0,
-            black_captures: 0,
-            white_captures: 0,
-            last_move: None,
+        // Now check if the placed stone (and its group) would have any liberties
+        !self.has_liberties_on_board(&test_board, x, y, color)
+    }
+
+    // Check liberties on a specific board state (for testing moves)
+    fn has_liberties_on_board(&self, board: &[[StoneState; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], x: usize, y: usize, color: StoneState) -> bool {
+        if board[y][x] != color {
+            return false;
+        }
+
+        let mut flood = self.flood.borrow_mut();
+        flood.reset();
+        flood.visit(x, y);
+        flood.stack.push((x, y));
+        while let Some((cx, cy)) = flood.stack.pop() {
+            for &(adj_x, adj_y) in self.neighbors(cx, cy) {
+                if board[adj_y][adj_x] == StoneState::Empty {
+                    return true; // Found a liberty
+                }
+                if board[adj_y][adj_x] == color && flood.visit(adj_x, adj_y) {
+                    flood.stack.push((adj_x, adj_y));
+                }
+            }
         }
+
+        false
     }
+}
 
-    pub fn get_board_state(&self, x: usize, y: usize) -> u8 {
-        if x >= self.board_size || y >= self.board_size {
-            return 0;
+// Liberty counts and predicted outcome of a capturing race, from semeai
+struct Semeai {
+    colors: (StoneState, StoneState),
+    liberties: (usize, usize),
+    outside: (usize, usize),  // Liberties of only that group, outside its eye
+    approach: (usize, usize), // Approach moves the opponent needs before filling them
+    eyes: (usize, usize),
+    shared: usize,
+    ko: bool,        // The pending ko point is one of the groups' liberties
+    unclear: bool,   // Big eyes or eye against eye: counting doesn't decide it
+    winner: i8,      // 1 = first group's owner, -1 = second's, 0 = seki or unclear
+    margin: usize,   // Moves the winner can play elsewhere and still win
+}
+
+// Differences between two games' current positions, from state_differences
+struct StateDifferences {
+    board_size: bool,
+    cells: Vec<(usize, usize, StoneState, StoneState)>, // (x, y, this, other)
+    current_player: bool,
+    captures: bool,
+}
+
+impl StateDifferences {
+    fn is_empty(&self) -> bool {
+        !self.board_size && self.cells.is_empty() && !self.current_player && !self.captures
+    }
+}
+
+// A group found by group_summaries: its size, liberty count and first stone
+struct GroupSummary {
+    color: StoneState,
+    stones: usize,
+    liberties: usize,
+    x: usize,
+    y: usize,
+}
+
+// CSS colors used by render(); the defaults match a traditional wooden board
+#[derive(Clone)]
+struct Theme {
+    board: String,
+    line: String,
+    black_stone: String,
+    white_stone: String,
+    marker: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            board: "#DEB887".to_string(),
+            line: "#000".to_string(),
+            black_stone: "#111".to_string(),
+            white_stone: "#F5F5F5".to_string(),
+            marker: "#FF6B6B".to_string(),
         }
-        match self.board[y][x] {
-            StoneState::Empty => 0,
-            StoneState::Black => 1,
-            StoneState::White => 2,
+    }
+}
+
+// Loose check that a string looks like a CSS color the canvas will accept: a #rgb(a) or
+// #rrggbb(aa) hex code, a color function such as rgb()/hsl(), or a named color
+fn is_css_color(color: &str) -> bool {
+    let color = color.trim();
+    if let Some(hex) = color.strip_prefix('#') {
+        return matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    if let Some(open) = color.find('(') {
+        let name = &color[..open];
+        return matches!(name, "rgb" | "rgba" | "hsl" | "hsla")
+            && color.ends_with(')')
+            && color[open + 1..color.len() - 1].chars().all(|c| c.is_ascii_digit() || " ,.%/-".contains(c));
+    }
+    !color.is_empty() && color.chars().all(|c| c.is_ascii_alphabetic())
+}
+
+// Outcome of a move attempt; the discriminant is the reason code exposed to JS
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MoveResult {
+    Success = 0,
+    OutOfBounds = 1,
+    Occupied = 2,
+    Suicide = 3,
+    OutsidePlayArea = 4,
+    InvalidColor = 5,
+    GuessModeActive = 6,
+    GameOver = 7,
+    Ko = 8,
+    InvalidState = 9,
+    ReviewActive = 10,
+    Superko = 11,
+    ScoringPhase = 12,
+}
+
+impl MoveResult {
+    // Human-readable message matching the strings handle_board_click has always returned
+    fn message(self) -> &'static str {
+        match self {
+            MoveResult::Success => "Move successful",
+            MoveResult::OutOfBounds => "Invalid move: Outside board bounds",
+            MoveResult::Occupied => "Invalid move: Position already occupied",
+            MoveResult::Suicide => "Invalid move: Cannot place stone that would be immediately captured (suicide rule)",
+            MoveResult::OutsidePlayArea => "Invalid move: Outside the active play area",
+            MoveResult::InvalidColor => "Invalid move: Color must be 1 (Black) or 2 (White)",
+            MoveResult::GuessModeActive => "Invalid move: Guess mode is active",
+            MoveResult::GameOver => "Invalid move: The game is over",
+            MoveResult::Ko => "Invalid move: Ko rule prohibits immediate recapture",
+            MoveResult::InvalidState => "Invalid move: State must be 0 (Empty), 1 (Black) or 2 (White)",
+            MoveResult::ReviewActive => "Invalid move: Reviewing; exit review to play",
+            MoveResult::Superko => "Invalid move: Superko rule prohibits repeating an earlier position",
+            MoveResult::ScoringPhase => "Invalid move: The game is being scored; play a stone to resume",
+        }
+    }
+}
+
+// Move representation for sequence encoding
+#[derive(Clone, Debug)]
+struct Move {
+    x: Option<usize>, // None for pass moves
+    y: Option<usize>, // None for pass moves
+    player: StoneState,
+    next_player: StoneState, // Player to move after this move (normally the opponent)
+    captured: Vec<(usize, usize)>, // Stones this move captured, filled in when played or replayed
+    took_ko: bool, // Captured a single stone in a ko, filled in with captured
+    atari: bool,   // Left an adjacent opponent group with one liberty, filled in with captured
+    quality: MoveQuality, // Reviewer annotation; travels with the move through undo/redo
+}
+
+// Reviewer judgement of a move, matching the SGF move annotation properties
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MoveQuality {
+    None = 0,
+    Good = 1,        // TE (tesuji, "!")
+    Bad = 2,         // BM (bad move, "?")
+    Interesting = 3, // IT ("!?")
+    Doubtful = 4,    // DO ("?!")
+}
+
+impl MoveQuality {
+    // The annotation with the given numeric value (0 = None ... 4 = Doubtful)
+    fn from_code(code: u8) -> Option<MoveQuality> {
+        match code {
+            0 => Some(MoveQuality::None),
+            1 => Some(MoveQuality::Good),
+            2 => Some(MoveQuality::Bad),
+            3 => Some(MoveQuality::Interesting),
+            4 => Some(MoveQuality::Doubtful),
+            _ => None,
+        }
+    }
+
+    // SGF property recording this annotation
+    fn sgf_property(self) -> Option<&'static str> {
+        match self {
+            MoveQuality::None => None,
+            MoveQuality::Good => Some("TE[1]"),
+            MoveQuality::Bad => Some("BM[1]"),
+            MoveQuality::Interesting => Some("IT[]"),
+            MoveQuality::Doubtful => Some("DO[]"),
+        }
+    }
+
+    // Conventional move-list symbol
+    fn symbol(self) -> &'static str {
+        match self {
+            MoveQuality::None => "",
+            MoveQuality::Good => "!",
+            MoveQuality::Bad => "?",
+            MoveQuality::Interesting => "!?",
+            MoveQuality::Doubtful => "?!",
+        }
+    }
+}
+
+// Where the game stands, as reported by get_game_phase
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GamePhase {
+    Playing = 0,  // Moves are being played
+    Scoring = 1,  // Two consecutive passes; dead stones can be marked
+    Finished = 2, // Decided by capture go or by accepting the score
+}
+
+// Which repetitions are forbidden when checking a move
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum KoRule {
+    None = 0,               // Anything goes
+    SimpleKo = 1,           // No immediate recapture of a single stone
+    PositionalSuperko = 2,  // No move may recreate any earlier board position
+    SituationalSuperko = 3, // As positional, but only with the same player to move
+}
+
+// How the handicap changes between the games of a match
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum HandicapRule {
+    Even = 0,            // Every game even, colors alternating
+    LoserGainsStone = 1, // The loser of each game receives one more stone in the next
+}
+
+// A multi-game match between player one and player two, who takes Black in the first game
+#[derive(Clone, Debug)]
+struct MatchState {
+    games: u32,
+    rule: HandicapRule,
+    base_komi: f32,    // Komi for even games
+    current_game: u32, // 1-based number of the game being played
+    results: Vec<u8>,  // Winner of each finished game: 1 = player one, 2 = player two
+}
+
+impl MatchState {
+    // Handicap level for the current game, from the results of the earlier ones: positive
+    // when player one receives, negative when player two does (1 = Black without komi,
+    // 2-9 = handicap stones)
+    fn handicap(&self) -> i32 {
+        if self.rule == HandicapRule::Even {
+            return 0;
+        }
+        self.results.iter().take(self.current_game as usize - 1).fold(0, |level, &winner| match winner {
+            1 => (level - 1).max(-9),
+            _ => (level + 1).min(9),
+        })
+    }
+
+    // The player (1 or 2) with Black in the current game: the receiver of any handicap,
+    // otherwise alternating from player one
+    fn black_player(&self) -> u8 {
+        match self.handicap() {
+            level if level > 0 => 1,
+            level if level < 0 => 2,
+            _ if self.current_game % 2 == 1 => 1,
+            _ => 2,
+        }
+    }
+
+    // Handicap stones and komi for the current game
+    fn game_setup(&self) -> (usize, f32) {
+        match self.handicap().unsigned_abs() as usize {
+            0 => (0, self.base_komi),
+            1 => (0, 0.5),
+            stones => (stones, 0.5),
+        }
+    }
+
+    fn wins(&self, player: u8) -> usize {
+        self.results.iter().filter(|&&winner| winner == player).count()
+    }
+
+    // Decided once a player has won a majority of the games, or all games are played
+    fn is_finished(&self) -> bool {
+        let majority = self.games as usize / 2 + 1;
+        self.wins(1) >= majority || self.wins(2) >= majority || self.results.len() >= self.games as usize
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut payload = vec![self.games as u8, self.rule as u8, self.current_game as u8, (self.base_komi * 2.0).round() as i8 as u8];
+        payload.extend_from_slice(&self.results);
+        payload
+    }
+
+    fn decode(payload: &[u8]) -> Option<MatchState> {
+        let (&[games, rule, current_game, komi], results) = payload.split_first_chunk::<4>()?;
+        let rule = match rule {
+            0 => HandicapRule::Even,
+            1 => HandicapRule::LoserGainsStone,
+            _ => return None,
+        };
+        let valid = games > 0
+            && current_game > 0
+            && current_game <= games
+            && results.len() <= current_game as usize
+            && results.len() + 1 >= current_game as usize
+            && results.iter().all(|&winner| winner == 1 || winner == 2);
+        valid.then(|| MatchState {
+            games: games as u32,
+            rule,
+            base_komi: komi as i8 as f32 / 2.0,
+            current_game: current_game as u32,
+            results: results.to_vec(),
+        })
+    }
+}
+
+// Summary of a serialized game read by GoGame::peek_state without loading it. States from a
+// newer format version only fill in the header fields (size, player to move, captures, move
+// count) and leave the rest at their defaults.
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct StateSummary {
+    #[wasm_bindgen(readonly)]
+    pub version: u8,
+    #[wasm_bindgen(readonly)]
+    pub supported: bool, // Whether this build can load the state (version not newer than ours)
+    #[wasm_bindgen(readonly, js_name = boardSize)]
+    pub board_size: u32,
+    #[wasm_bindgen(readonly, js_name = moveCount)]
+    pub move_count: u32,
+    #[wasm_bindgen(readonly, js_name = currentPlayer)]
+    pub current_player: u8, // 1 = Black, 2 = White
+    #[wasm_bindgen(readonly, js_name = blackCaptures)]
+    pub black_captures: u32,
+    #[wasm_bindgen(readonly, js_name = whiteCaptures)]
+    pub white_captures: u32,
+    #[wasm_bindgen(readonly, js_name = hasSetup)]
+    pub has_setup: bool, // Handicap or setup stones before the first move
+    #[wasm_bindgen(readonly, js_name = matchGame)]
+    pub match_game: u32, // Game number within a match, 0 outside a match
+    #[wasm_bindgen(readonly, js_name = matchGames)]
+    pub match_games: u32,
+}
+
+// The most recent action in the game, distinguishing passes from "no moves yet"
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum LastAction {
+    None,
+    Stone(usize, usize),
+    Pass(StoneState),
+}
+
+// Why a game ended (see GoGame::is_game_over)
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum GameOverReason {
+    CaptureGo,     // A player reached the capture go target (see get_winner)
+    ScoreAccepted, // The score was accepted at the current move
+    Void,          // A repetition cycle, or a capture go game ended by two passes
+}
+
+impl GameOverReason {
+    // The reason string get_game_over_reason and the on_game_over callback report to JS
+    fn message(self) -> &'static str {
+        match self {
+            GameOverReason::CaptureGo => "Capture Go",
+            GameOverReason::ScoreAccepted => "Score accepted",
+            GameOverReason::Void => "Void/No result",
+        }
+    }
+}
+
+// A board intersection, exported to JS as a small class
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Point {
+    #[wasm_bindgen(readonly)]
+    pub x: u32,
+    #[wasm_bindgen(readonly)]
+    pub y: u32,
+}
+
+// A move as seen by JS: x = y = -1 for passes, player 1 = Black, 2 = White
+#[wasm_bindgen]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MoveInfo {
+    #[wasm_bindgen(readonly)]
+    pub x: i32,
+    #[wasm_bindgen(readonly)]
+    pub y: i32,
+    #[wasm_bindgen(readonly)]
+    pub player: u8,
+    #[wasm_bindgen(readonly, js_name = isPass)]
+    pub is_pass: bool,
+    #[wasm_bindgen(readonly, js_name = moveNumber)]
+    pub move_number: u32,
+    // What the move did, for move-list icons; zero/false for moves not yet played through
+    #[wasm_bindgen(readonly, js_name = capturedCount)]
+    pub captured_count: u32,
+    #[wasm_bindgen(readonly, js_name = wasKoCapture)]
+    pub was_ko_capture: bool,
+    #[wasm_bindgen(readonly, js_name = putOpponentInAtari)]
+    pub put_opponent_in_atari: bool,
+}
+
+// The live game saved by enter_review and restored exactly by exit_review
+#[derive(Clone)]
+struct ReviewSnapshot {
+    board: Board,
+    move_numbers: [[u32; MAX_BOARD_SIZE]; MAX_BOARD_SIZE],
+    current_player: StoneState,
+    move_sequence: Vec<Move>,
+    move_index: usize,
+    black_captures: u32,
+    white_captures: u32,
+    last_action: LastAction,
+    position_edited: bool,
+    dead_stones: Vec<(usize, usize)>,
+    hash_history: Vec<(u64, StoneState)>,
+    discarded_branches: Vec<DiscardedBranch>,
+}
+
+// Undone moves that were replaced by a new move while branch preservation was enabled
+#[derive(Clone, Debug)]
+struct DiscardedBranch {
+    name: String,
+    from_index: usize, // Number of moves played before the branch diverged
+    moves: Vec<Move>,
+}
+
+// Simple Go game struct without WebGPU for now
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct GoGame {
+    board: Board, // Stones, ko and the rules that change them
+    move_numbers: [[u32; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], // Track move number for each position (0 = no move)
+    current_player: StoneState,
+    canvas: Option<HtmlCanvasElement>, // Render target (None for scratch games)
+    canvas_width: u32,
+    canvas_height: u32,
+    device_pixel_ratio: f64, // Backing-store pixels per CSS pixel
+    click_tolerance: f32, // Normalized margin beyond the board edge that handle_click accepts
+    move_sequence: Vec<Move>, // Chronological sequence of moves - replaces history
+    move_index: usize, // Current position in move sequence (for undo/redo)
+    black_captures: u32,
+    white_captures: u32,
+    last_action: LastAction, // Track the last move (stone or pass)
+    play_area: Option<(usize, usize, usize, usize)>, // Inclusive (x0, y0, x1, y1) rectangle moves are restricted to
+    serialize_play_area: bool, // Include the play area in serialized state (off by default)
+    serialize_checksum: bool, // Append a position checksum to serialized state (on by default)
+    dead_stones: Vec<(usize, usize)>, // Stones marked dead for scoring (cleared when the position changes)
+    autoplay_interval: Option<u32>, // Milliseconds per move while autoplay is running
+    autoplay_last_step: Option<f64>, // Timestamp of the last autoplay step (None until the first tick)
+    free_placement: bool, // Teaching mode: place placement_color without alternating turns
+    placement_color: StoneState,
+    one_color_mode: bool, // Presentation filter: report all stones as black
+    reveal: bool, // Bypass presentation filters
+    blind_moves: usize, // Blindfold mode: hide the stones of this many recent moves (0 = off)
+    blind_start: usize, // Move index when blindfold mode was enabled
+    guess_mode: bool, // Guess-the-move training over the loaded game
+    review: Option<ReviewSnapshot>, // Live game saved while reviewing (None when not reviewing)
+    ko_rule: KoRule,
+    pass_accepts_score: bool, // Whether a pass during scoring accepts the dead-stone marking
+    score_accepted_at: Option<usize>, // Move index at which the score was accepted
+    position_edited: bool, // Edit mode changed the board since it was last rebuilt from the history
+    debug_checks: bool, // Run check_invariants after every change (opt-in: each check replays the game)
+    rng: Rng, // Seeded generator for random choices (see set_random_seed)
+    free_handicap: usize, // Black plays this many opening moves in a row (manual free handicap)
+    repetition_limit: u32, // Without superko, the game is void once a position occurs this often (0 = never)
+    repetition_void: bool, // The current position has reached the repetition limit
+    guess_total: u32,
+    guess_correct: u32,
+    guess_streak: u32,
+    guess_best_streak: u32,
+    setup_board: [[StoneState; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], // Handicap/setup stones present before the first move
+    setup_player: StoneState, // Player to move in the setup position
+    komi: f32, // Points added to White's score
+    game_match: Option<MatchState>, // Multi-game match this game belongs to
+    show_move_numbers: bool, // Draw move numbers on stones in render()
+    theme: Theme, // Colors used by render()
+    capture_go_target: u32, // Capture Go: captures needed to win (0 = normal game)
+    hash_history: Vec<(u64, StoneState)>, // Board hash and player to move after each move index
+    preserve_branches: bool, // Keep truncated future moves instead of discarding them
+    discarded_branches: Vec<DiscardedBranch>,
+    on_move_callback: Option<js_sys::Function>,
+    on_capture_callback: Option<js_sys::Function>,
+    on_turn_change_callback: Option<js_sys::Function>,
+    on_game_over_callback: Option<js_sys::Function>,
+    frame_board: Vec<u8>, // Backing store for board_view (allocated on first use)
+    frame_move_numbers: Vec<u32>, // Backing store for move_number_view (allocated on first use)
+}
+
+#[wasm_bindgen]
+impl GoGame {
+    #[wasm_bindgen(constructor)]
+    pub fn new(canvas: HtmlCanvasElement) -> GoGame {
+        Self::new_with_size(canvas, 19)
+    }
+
+    pub fn new_with_size(canvas: HtmlCanvasElement, board_size: usize) -> GoGame {
+        console_log!("Initializing Go game with {}x{} board...", board_size, board_size);
+
+        // Initialize logging
+        console_error_panic_hook::set_once();
+
+        let valid_size = match board_size {
+            _ if is_supported_board_size(board_size) => board_size,
+            _ => {
+                console_log!("Invalid board size {}, defaulting to 19x19", board_size);
+                19
+            }
+        };
+
+        let mut game = Self::blank(valid_size);
+        game.canvas_width = canvas.width();
+        game.canvas_height = canvas.height();
+        game.canvas = Some(canvas);
+        game
+    }
+
+    // Fallible constructor: throws for unsupported board sizes instead of silently
+    // substituting 19x19 like new_with_size does
+    pub fn try_new_with_size(canvas: HtmlCanvasElement, board_size: usize) -> Result<GoGame, JsError> {
+        if !is_supported_board_size(board_size) {
+            return Err(JsError::new(&format!("Unsupported board size {}", board_size)));
+        }
+        Ok(Self::new_with_size(canvas, board_size))
+    }
+
+    // Create a fully independent copy of this game (board, history, captures and settings)
+    // for "what if" analysis; changes to the clone never affect the original
+    pub fn clone_game(&self) -> GoGame {
+        let mut clone = self.clone();
+        // The canvas and listeners belong to the original game's UI
+        clone.canvas = None;
+        clone.on_move_callback = None;
+        clone.on_capture_callback = None;
+        clone.on_turn_change_callback = None;
+        clone.on_game_over_callback = None;
+        clone
+    }
+
+    // Create an empty game not attached to any canvas (used for scratch computations)
+    fn blank(board_size: usize) -> GoGame {
+        let initial_board = [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        let initial_move_numbers = [[0u32; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+
+        GoGame {
+            board: Board::new(board_size),
+            move_numbers: initial_move_numbers,
+            current_player: StoneState::Black,
+            canvas: None,
+            canvas_width: 0,
+            canvas_height: 0,
+            device_pixel_ratio: 1.0,
+            click_tolerance: 0.05,
+            move_sequence: Vec::new(),
+            move_index: 0,
+            black_captures: 0,
+            white_captures: 0,
+            last_action: LastAction::None,
+            play_area: None,
+            serialize_play_area: false,
+            serialize_checksum: true,
+            dead_stones: Vec::new(),
+            autoplay_interval: None,
+            autoplay_last_step: None,
+            free_placement: false,
+            placement_color: StoneState::Black,
+            one_color_mode: false,
+            reveal: false,
+            blind_moves: 0,
+            blind_start: 0,
+            guess_mode: false,
+            review: None,
+            ko_rule: KoRule::SimpleKo,
+            pass_accepts_score: true,
+            score_accepted_at: None,
+            position_edited: false,
+            debug_checks: false,
+            rng: Rng::new(0),
+            free_handicap: 0,
+            repetition_limit: 3,
+            repetition_void: false,
+            guess_total: 0,
+            guess_correct: 0,
+            guess_streak: 0,
+            guess_best_streak: 0,
+            setup_board: initial_board,
+            setup_player: StoneState::Black,
+            komi: 7.5,
+            game_match: None,
+            show_move_numbers: false,
+            theme: Theme::default(),
+            capture_go_target: 0,
+            hash_history: vec![(0, StoneState::Black)],
+            preserve_branches: false,
+            discarded_branches: Vec::new(),
+            on_move_callback: None,
+            on_capture_callback: None,
+            on_turn_change_callback: None,
+            on_game_over_callback: None,
+            frame_board: Vec::new(),
+            frame_move_numbers: Vec::new(),
+        }
+    }
+
+    pub fn get_board_state(&self, x: usize, y: usize) -> u8 {
+        if x >= self.board.size || y >= self.board.size {
+            return 0;
+        }
+        self.displayed_stone(x, y)
+    }
+
+    // Get the whole board as a row-major array of displayed states (0 = empty, 1 = black, 2 = white)
+    pub fn get_board_flat(&self) -> Box<[u8]> {
+        let mut flat = Vec::with_capacity(self.board.size * self.board.size);
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                flat.push(self.displayed_stone(x, y));
+            }
+        }
+        flat.into_boxed_slice()
+    }
+
+    // The setup position (handicap and setup stones, before any move in the sequence) as a
+    // row-major array like get_board_flat, with the same presentation filter (setup stones
+    // have no move number, so blind mode never hides them); this is what
+    // reconstruct_state_to_index(0) restores
+    pub fn get_setup_snapshot(&self) -> Box<[u8]> {
+        let mut flat = Vec::with_capacity(self.board.size * self.board.size);
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                flat.push(self.displayed_player(self.setup_board[y][x]));
+            }
+        }
+        flat.into_boxed_slice()
+    }
+
+    // Allocation-free per-frame access to the board: refreshes an internal buffer of
+    // board_size * board_size displayed states (as in get_board_flat) and returns its address
+    // in wasm memory, for a view like `new Uint8Array(memory.buffer, ptr, size * size)`.
+    // The buffer never moves, but recreate the view each frame in case memory has grown.
+    pub fn board_view(&mut self) -> *const u8 {
+        self.frame_board.resize(MAX_BOARD_SIZE * MAX_BOARD_SIZE, 0);
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                self.frame_board[y * self.board.size + x] = self.displayed_stone(x, y);
+            }
+        }
+        self.frame_board.as_ptr()
+    }
+
+    // Like board_view, for the move numbers (as in get_move_number) as a Uint32Array
+    pub fn move_number_view(&mut self) -> *const u32 {
+        self.frame_move_numbers.resize(MAX_BOARD_SIZE * MAX_BOARD_SIZE, 0);
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                self.frame_move_numbers[y * self.board.size + x] = self.get_move_number(x, y);
+            }
+        }
+        self.frame_move_numbers.as_ptr()
+    }
+
+    // Play a move for the current player and return the result in one call: the first byte
+    // is the MoveResult code, followed by the get_board_flat() snapshot when the move succeeded
+    pub fn play_and_snapshot(&mut self, x: usize, y: usize) -> Box<[u8]> {
+        let result = self.play_move(x, y);
+        let mut snapshot = vec![result as u8];
+        if result == MoveResult::Success {
+            snapshot.extend_from_slice(&self.get_board_flat());
+        }
+        snapshot.into_boxed_slice()
+    }
+
+    // Describe an intersection for hover tooltips: the displayed stone (player 0 if empty)
+    // and the move number that placed it. Returns None outside the board.
+    pub fn get_hover_info(&self, x: usize, y: usize) -> Option<MoveInfo> {
+        if x >= self.board.size || y >= self.board.size {
+            return None;
+        }
+        Some(MoveInfo {
+            x: x as i32,
+            y: y as i32,
+            player: self.displayed_stone(x, y),
+            is_pass: false,
+            move_number: self.get_move_number(x, y),
+            captured_count: 0,
+            was_ko_capture: false,
+            put_opponent_in_atari: false,
+        })
+    }
+
+    // Deprecated: JSON form of get_hover_info, {"x","y","label","state","move_number"}
+    pub fn get_hover_info_json(&self, x: usize, y: usize) -> String {
+        if x >= self.board.size || y >= self.board.size {
+            return "null".to_string();
+        }
+        format!(
+            "{{\"x\":{},\"y\":{},\"label\":\"{}\",\"state\":{},\"move_number\":{}}}",
+            x,
+            y,
+            self.coord_to_label(x, y),
+            self.displayed_stone(x, y),
+            self.get_move_number(x, y)
+        )
+    }
+
+    // One-color Go: accessors report every stone as black while the engine keeps true colors
+    pub fn set_one_color_mode(&mut self, enabled: bool) {
+        self.one_color_mode = enabled;
+    }
+
+    pub fn is_one_color_mode(&self) -> bool {
+        self.one_color_mode
+    }
+
+    // Temporarily show the true position while a presentation filter is active
+    pub fn set_reveal(&mut self, reveal: bool) {
+        self.reveal = reveal;
+    }
+
+    // Blindfold mode: hide the stones of the last n moves from the accessors (0 disables).
+    // Only moves played after the mode was enabled are ever hidden.
+    pub fn set_blind_moves(&mut self, n: usize) {
+        if self.blind_moves == 0 && n > 0 {
+            self.blind_start = self.move_index;
+        }
+        self.blind_moves = n;
+    }
+
+    pub fn get_blind_moves(&self) -> usize {
+        self.blind_moves
+    }
+
+    // Whether the stone at (x, y) is currently hidden by blindfold mode
+    fn is_blind_hidden(&self, x: usize, y: usize) -> bool {
+        if self.blind_moves == 0 || self.reveal {
+            return false;
+        }
+        let hide_after = self.move_index.saturating_sub(self.blind_moves).max(self.blind_start);
+        self.move_numbers[y][x] as usize > hide_after
+    }
+
+    // Stone state as presented to the UI, after applying presentation filters
+    fn displayed_stone(&self, x: usize, y: usize) -> u8 {
+        if self.is_blind_hidden(x, y) {
+            return 0;
+        }
+        self.displayed_player(self.board[y][x])
+    }
+
+    // Player color as presented to the UI (one-color mode reports every stone as black)
+    fn displayed_player(&self, player: StoneState) -> u8 {
+        match player {
+            StoneState::Empty => 0,
+            _ if self.one_color_mode && !self.reveal => 1,
+            StoneState::Black => 1,
+            StoneState::White => 2,
+        }
+    }
+
+    pub fn get_board_size(&self) -> usize {
+        self.board.size
+    }
+
+    // Switch to a new supported board size, starting an empty game: the move sequence,
+    // setup stones, captures and any guess session or match are cleared, while the canvas,
+    // callbacks and display settings stay. Unsupported sizes are rejected unchanged.
+    pub fn set_board_size(&mut self, size: usize) -> bool {
+        if !is_supported_board_size(size) {
+            return false;
+        }
+
+        self.stop_autoplay();
+        self.board.set_size(size);
+        self.review = None;
+        self.score_accepted_at = None;
+        self.free_handicap = 0;
+        self.move_sequence.clear();
+        self.move_index = 0;
+        self.discarded_branches.clear();
+        self.setup_board = [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        self.setup_player = StoneState::Black;
+        self.play_area = None;
+        self.guess_mode = false;
+        self.blind_start = 0;
+        self.game_match = None;
+        self.reconstruct_state_to_index(0);
+
+        console_log!("Board size set to {}x{}", size, size);
+        self.notify_turn_change();
+        true
+    }
+
+    pub fn get_current_player(&self) -> u8 {
+        match self.current_player {
+            StoneState::Black => 1,
+            StoneState::White => 2,
+            StoneState::Empty => 0,
+        }
+    }
+
+    pub fn get_move_number(&self, x: usize, y: usize) -> u32 {
+        if x >= self.board.size || y >= self.board.size || self.is_blind_hidden(x, y) {
+            return 0;
+        }
+        self.move_numbers[y][x]
+    }
+
+    // Convert board coordinates to conventional notation, e.g. (3, 15) on 19x19 -> "D4".
+    // Columns skip the letter I; rows count up from the bottom edge.
+    pub fn coord_to_label(&self, x: usize, y: usize) -> String {
+        if x >= self.board.size || y >= self.board.size {
+            return String::new();
+        }
+        format!("{}{}", COLUMN_LETTERS[x] as char, self.board.size - y)
+    }
+
+    // Comma-separated column labels from left to right (e.g. "A,B,...,H,J,...")
+    pub fn get_column_labels(&self) -> String {
+        COLUMN_LETTERS[..self.board.size]
+            .iter()
+            .map(|&c| (c as char).to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    // Comma-separated row labels from top to bottom (e.g. "19,18,...,1")
+    pub fn get_row_labels(&self) -> String {
+        (1..=self.board.size)
+            .rev()
+            .map(|row| row.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    // Plain-text diagram of the position for pasting into forums: `X` black, `O` white,
+    // `.` empty and `+` star points, with column letters above and below and row numbers on
+    // both sides. The last stone is drawn as `#` (black) or `@` (white); with `mark_dead`,
+    // stones marked dead are drawn in lowercase (`x`, `o`).
+    pub fn to_diagram(&self, mark_dead: bool) -> String {
+        let width = self.board.size.to_string().len();
+        let stars = self.star_points();
+        let last = self.last_stone();
+
+        let columns: Vec<String> = COLUMN_LETTERS[..self.board.size].iter().map(|&c| (c as char).to_string()).collect();
+        let header = format!("{:width$}  {}\n", "", columns.join(" "));
+
+        let mut diagram = header.clone();
+        for y in 0..self.board.size {
+            let row = self.board.size - y;
+            let cells: Vec<&str> = (0..self.board.size)
+                .map(|x| {
+                    let dead = mark_dead && self.is_dead_stone(x, y);
+                    match self.displayed_stone(x, y) {
+                        1 if last == Some((x, y)) => "#",
+                        2 if last == Some((x, y)) => "@",
+                        1 if dead => "x",
+                        2 if dead => "o",
+                        1 => "X",
+                        2 => "O",
+                        _ if stars.contains(&(x, y)) => "+",
+                        _ => ".",
+                    }
+                })
+                .collect();
+            diagram.push_str(&format!("{:>width$}  {}  {}\n", row, cells.join(" "), row));
+        }
+        diagram.push_str(&header);
+        diagram
+    }
+
+    // Reconstruct game state from move sequence up to move_index
+    fn reconstruct_state_to_index(&mut self, target_index: usize) {
+        let previous_player = self.current_player;
+
+        // Reset to the setup position (empty unless handicap or setup stones were placed)
+        self.board.stones = self.setup_board;
+        self.move_numbers = [[0u32; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        self.current_player = self.setup_player;
+        self.black_captures = 0;
+        self.white_captures = 0;
+        self.last_action = LastAction::None;
+        self.board.ko = None;
+        self.dead_stones.clear();
+        self.position_edited = false;
+        self.hash_history = vec![(self.board_hash(), self.current_player)];
+
+        // Collect moves to avoid borrow checker issues
+        let moves_to_replay: Vec<Move> = self.move_sequence.iter().take(target_index).cloned().collect();
+
+        // Replay moves up to target_index
+        for (i, mv) in moves_to_replay.iter().enumerate() {
+            match (mv.x, mv.y) {
+                (Some(x), Some(y)) => {
+                    // Stone placement move, sharing the capture logic with live play
+                    let captured = self.apply_stone(x, y, mv.player, (i + 1) as u32);
+                    self.record_stone_effects(i, x, y, captured);
+                }
+                (None, None) => {
+                    // Pass move (lifts any ko)
+                    self.last_action = LastAction::Pass(mv.player);
+                    self.board.ko = None;
+                }
+                (None, Some(_)) | (Some(_), None) => {
+                    // Invalid move data - this should never happen in a properly constructed move sequence
+                    console_log!("Warning: Invalid move data encountered during state reconstruction");
+                }
+            }
+
+            // Update current player for next move
+            self.current_player = mv.next_player;
+            self.hash_history.push((self.board_hash(), self.current_player));
+        }
+
+        self.update_repetition();
+        self.run_debug_checks("navigation");
+        if self.current_player != previous_player {
+            self.notify_turn_change();
+        }
+    }
+
+    // Play the current player's stone at normalized click coordinates (see resolve_click),
+    // through the same rules, history and callbacks as play_move
+    pub fn handle_click(&mut self, x: f32, y: f32) -> MoveResult {
+        console_log!("Click at ({}, {})", x, y);
+        let Some((board_x, board_y)) = self.click_to_intersection(x, y) else {
+            return MoveResult::OutOfBounds;
+        };
+
+        self.play_move(board_x, board_y)
+    }
+
+    // The intersection handle_click would use for normalized coordinates (-1 to 1 across the
+    // board, edge lines at exactly -1 and 1), or None for clicks beyond the click tolerance
+    pub fn resolve_click(&self, x: f32, y: f32) -> Option<Point> {
+        self.click_to_intersection(x, y).map(|(x, y)| Point { x: x as u32, y: y as u32 })
+    }
+
+    fn click_to_intersection(&self, x: f32, y: f32) -> Option<(usize, usize)> {
+        // Clicks in the margin beyond the tolerance are off-board, not snapped to the edge
+        // (NaN fails the range check too)
+        let limit = 1.0 + self.click_tolerance;
+        if !(-limit..=limit).contains(&x) || !(-limit..=limit).contains(&y) {
+            return None;
+        }
+
+        // Clamp the margin onto the edge lines first, then round to the nearest line, so
+        // both edges behave the same whatever the caller's normalization
+        let max = (self.board.size - 1) as f32;
+        let to_line = |v: f32| ((v.clamp(-1.0, 1.0) + 1.0) / 2.0 * max).round() as usize;
+        Some((to_line(x), to_line(y)))
+    }
+
+    // How far beyond the -1..1 normalized board edge handle_click still snaps to the edge
+    // intersections; clicks further out are rejected as off-board. Negative values are treated as 0.
+    pub fn set_click_tolerance(&mut self, tolerance: f32) {
+        self.click_tolerance = if tolerance.is_finite() { tolerance.max(0.0) } else { 0.0 };
+    }
+
+    pub fn handle_board_click(&mut self, board_x: usize, board_y: usize) -> String {
+        self.play_move(board_x, board_y).message().to_string()
+    }
+
+    // Play a stone for the current player, returning a reason code instead of a message
+    pub fn play_move(&mut self, board_x: usize, board_y: usize) -> MoveResult {
+        console_log!("Board click at ({}, {})", board_x, board_y);
+
+        let next_player = if self.free_placement {
+            // Teaching mode: always place the configured color and don't alternate
+            self.placement_color
+        } else if self.move_index + 1 < self.free_handicap {
+            // Black still has free handicap stones to place
+            StoneState::Black
+        } else {
+            match self.current_player {
+                StoneState::Black => StoneState::White,
+                StoneState::White => StoneState::Black,
+                StoneState::Empty => StoneState::Black,
+            }
+        };
+        let placed_stone = self.stone_to_place();
+
+        let result = self.place_stone_for(board_x, board_y, placed_stone, next_player);
+        if result == MoveResult::Success && self.free_handicap > 0 && self.move_index == self.free_handicap {
+            // Black's last free handicap stone: like a fixed handicap, the stones become the
+            // setup position, so undo can't step back into them
+            self.reset_move_numbering(true);
+        }
+        result
+    }
+
+    // The color a click would place: the placement color in free placement, else the player to move
+    fn stone_to_place(&self) -> StoneState {
+        if self.free_placement { self.placement_color } else { self.current_player }
+    }
+
+    // Play a stone as an explicit color (1 = Black, 2 = White), e.g. when replaying records
+    // where the source is authoritative about who moved. The opponent of that color moves next.
+    pub fn play_move_for(&mut self, color: u8, board_x: usize, board_y: usize) -> MoveResult {
+        let (player, next_player) = match color {
+            1 => (StoneState::Black, StoneState::White),
+            2 => (StoneState::White, StoneState::Black),
+            _ => return MoveResult::InvalidColor,
+        };
+
+        self.place_stone_for(board_x, board_y, player, next_player)
+    }
+
+    // Place a stone of an explicit color (1 = Black, 2 = White) through the normal rules
+    // without handing the turn to the opponent, so handicap and teaching sequences can play
+    // several stones of one color in a row. The player to move stays unchanged.
+    pub fn place_stone(&mut self, x: usize, y: usize, color: u8) -> String {
+        let player = match color {
+            1 => StoneState::Black,
+            2 => StoneState::White,
+            _ => return MoveResult::InvalidColor.message().to_string(),
+        };
+
+        self.place_stone_for(x, y, player, self.current_player).message().to_string()
+    }
+
+    // Check whether a stone of the given color may be played at (board_x, board_y) without
+    // changing anything; Success means the move is legal
+    fn check_move(&self, board_x: usize, board_y: usize, placed_stone: StoneState) -> MoveResult {
+        if self.guess_mode {
+            // The loaded game must not be altered while guessing through it
+            return MoveResult::GuessModeActive;
+        }
+
+        if self.review.is_some() {
+            return MoveResult::ReviewActive;
+        }
+
+        if self.is_game_over() {
+            return MoveResult::GameOver;
+        }
+
+        if board_x >= self.board.size || board_y >= self.board.size {
+            return MoveResult::OutOfBounds;
+        }
+
+        if !self.is_in_play_area(board_x, board_y) {
+            return MoveResult::OutsidePlayArea;
+        }
+
+        // Occupied, ko and suicide; simple ko is also a repetition under either superko
+        // rule, reported as plain ko
+        if let Err(result) = self.board.check_placement(board_x, board_y, placed_stone, self.ko_rule != KoRule::None) {
+            return result;
+        }
+
+        if matches!(self.ko_rule, KoRule::PositionalSuperko | KoRule::SituationalSuperko)
+            && self.repeats_position(board_x, board_y, placed_stone)
+        {
+            return MoveResult::Superko;
+        }
+
+        MoveResult::Success
+    }
+
+    // Validate and apply a stone of the given color (captures, history, move numbers),
+    // hand the turn to next_player and notify any registered callbacks
+    fn place_stone_for(&mut self, board_x: usize, board_y: usize, placed_stone: StoneState, next_player: StoneState) -> MoveResult {
+        let check = self.check_move(board_x, board_y, placed_stone);
+        if check != MoveResult::Success {
+            console_log!("Rejected {:?} stone at ({}, {}): {:?}", placed_stone, board_x, board_y, check);
+            return check;
+        }
+
+        // Remove any future moves if we're not at the end (truncate for new branch)
+        self.truncate_future();
+        self.dead_stones.clear();
+        self.score_accepted_at = None;
+
+        // Add move to sequence
+        self.move_sequence.push(Move {
+            x: Some(board_x),
+            y: Some(board_y),
+            player: placed_stone,
+            next_player,
+            captured: Vec::new(),
+            took_ko: false,
+            atari: false,
+            quality: MoveQuality::None,
+        });
+        self.move_index += 1;
+
+        // Place the stone and resolve captures
+        let captured_stones = self.apply_stone(board_x, board_y, placed_stone, self.move_index as u32);
+        let total_captured = captured_stones.len() as u32;
+        self.record_stone_effects(self.move_index - 1, board_x, board_y, captured_stones.clone());
+
+        if total_captured > 0 {
+            console_log!("Captured {} stones", total_captured);
+        }
+
+        let previous_player = self.current_player;
+        self.current_player = next_player;
+        self.record_position();
+
+        console_log!("Placed {:?} stone at ({}, {}), move index: {}", placed_stone, board_x, board_y, self.move_index);
+
+        // Notify listeners only once the engine state is fully updated
+        self.notify_move(board_x as i32, board_y as i32, placed_stone);
+        if !captured_stones.is_empty() {
+            self.notify_capture(placed_stone, &captured_stones);
+        }
+        if self.current_player != previous_player {
+            self.notify_turn_change();
+        }
+        self.notify_game_over();
+
+        MoveResult::Success
+    }
+
+    // Register a callback fired after every successful stone placement or pass as
+    // cb(x, y, player), with x = y = -1 for a pass. Pass null to unregister.
+    pub fn on_move(&mut self, callback: Option<js_sys::Function>) {
+        self.on_move_callback = callback;
+    }
+
+    // Register a callback fired after a move captures stones as cb(capturing_player, stones),
+    // where stones is an array of Point. Pass null to unregister.
+    pub fn on_capture(&mut self, callback: Option<js_sys::Function>) {
+        self.on_capture_callback = callback;
+    }
+
+    // Register a callback fired as cb(player) whenever the player to move changes.
+    // Pass null to unregister.
+    pub fn on_turn_change(&mut self, callback: Option<js_sys::Function>) {
+        self.on_turn_change_callback = callback;
+    }
+
+    // Register a callback fired as cb(reason, captured) when a move or pass ends the game,
+    // with the reason from get_game_over_reason and the stones ({x, y}) captured by the
+    // ending move, e.g. the capture that decided a capture go game. Pass null to unregister.
+    pub fn on_game_over(&mut self, callback: Option<js_sys::Function>) {
+        self.on_game_over_callback = callback;
+    }
+
+    fn notify_move(&self, x: i32, y: i32, player: StoneState) {
+        if let Some(callback) = &self.on_move_callback {
+            let args = js_sys::Array::of3(&x.into(), &y.into(), &(player as u8).into());
+            call_listener("on_move", callback, &args);
+        }
+    }
+
+    fn notify_capture(&self, player: StoneState, stones: &[(usize, usize)]) {
+        if let Some(callback) = &self.on_capture_callback {
+            let points: js_sys::Array = stones
+                .iter()
+                .map(|&(x, y)| JsValue::from(Point { x: x as u32, y: y as u32 }))
+                .collect();
+            let args = js_sys::Array::of2(&(player as u8).into(), &points);
+            call_listener("on_capture", callback, &args);
+        }
+    }
+
+    fn notify_turn_change(&self) {
+        if let Some(callback) = &self.on_turn_change_callback {
+            let args = js_sys::Array::of1(&self.get_current_player().into());
+            call_listener("on_turn_change", callback, &args);
+        }
+    }
+
+    // Tell the listener if the game is over (called after the move or pass that ended it)
+    fn notify_game_over(&self) {
+        if let (Some(callback), Some(reason)) = (&self.on_game_over_callback, self.game_over_reason()) {
+            let captured: js_sys::Array = self.move_sequence[..self.move_index]
+                .last()
+                .map_or(&[][..], |mv| &mv.captured)
+                .iter()
+                .map(|&(x, y)| JsValue::from(Point { x: x as u32, y: y as u32 }))
+                .collect();
+            let args = js_sys::Array::of2(&reason.message().into(), &captured);
+            call_listener("on_game_over", callback, &args);
+        }
+    }
+
+    // Append the current position to the hash history (one entry per played move)
+    fn record_position(&mut self) {
+        self.hash_history.truncate(self.move_index);
+        self.hash_history.push((self.board_hash(), self.current_player));
+        self.update_repetition();
+        self.run_debug_checks("move");
+    }
+
+    // Recheck whether the current position, with the same player to move, has now occurred
+    // repetition_limit times. Only without superko, which already forbids repeating.
+    fn update_repetition(&mut self) {
+        let superko = matches!(self.ko_rule, KoRule::PositionalSuperko | KoRule::SituationalSuperko);
+        let was_void = self.repetition_void;
+        self.repetition_void = !superko
+            && self.repetition_limit > 0
+            && self.hash_history.get(self.move_index).is_some_and(|current| {
+                self.hash_history[..=self.move_index].iter().filter(|&entry| entry == current).count() >= self.repetition_limit as usize
+            });
+        if self.repetition_void && !was_void {
+            console_log!("Position repeated {} times: the game is void", self.repetition_limit);
+        }
+    }
+
+    // Without a superko rule (e.g. under Japanese rules), end the game as void once the same
+    // position with the same player to move occurs `times` times, as in a triple ko or
+    // eternal life. Defaults to 3; 0 lets cycles go on forever.
+    pub fn set_repetition_limit(&mut self, times: u32) {
+        self.repetition_limit = times;
+        self.update_repetition();
+    }
+
+    pub fn get_repetition_limit(&self) -> u32 {
+        self.repetition_limit
+    }
+
+    // Whether a stone of `color` at empty (x, y) would recreate a position from the game so
+    // far (with the opponent to move, under situational superko)
+    fn repeats_position(&self, x: usize, y: usize, color: StoneState) -> bool {
+        let key = |x: usize, y: usize, color: StoneState| ZOBRIST_KEYS[y * MAX_BOARD_SIZE + x][if color == StoneState::Black { 0 } else { 1 }];
+        let opponent = opponent_of(color);
+
+        let mut board = self.board.stones;
+        board[y][x] = color;
+        let mut hash = self.hash_history[self.move_index].0 ^ key(x, y, color);
+        for &(adj_x, adj_y) in self.board.neighbors(x, y) {
+            if board[adj_y][adj_x] != opponent || self.board.has_liberties_on_board(&board, adj_x, adj_y, opponent) {
+                continue;
+            }
+            // Remove the captured group, updating the hash as each stone goes
+            let mut stack = vec![(adj_x, adj_y)];
+            board[adj_y][adj_x] = StoneState::Empty;
+            while let Some((cx, cy)) = stack.pop() {
+                hash ^= key(cx, cy, opponent);
+                for &(nx, ny) in self.board.neighbors(cx, cy) {
+                    if board[ny][nx] == opponent {
+                        board[ny][nx] = StoneState::Empty;
+                        stack.push((nx, ny));
+                    }
+                }
+            }
+        }
+
+        let situational = self.ko_rule == KoRule::SituationalSuperko;
+        self.hash_history[..=self.move_index]
+            .iter()
+            .any(|&(earlier, to_move)| earlier == hash && (!situational || to_move == opponent))
+    }
+
+    // Choose the repetition rule for new moves (see KoRule: 0 = none, 1 = simple ko,
+    // 2 = positional superko, 3 = situational superko); false for unknown values
+    pub fn set_ko_rule(&mut self, rule: u8) -> bool {
+        self.ko_rule = match rule {
+            0 => KoRule::None,
+            1 => KoRule::SimpleKo,
+            2 => KoRule::PositionalSuperko,
+            3 => KoRule::SituationalSuperko,
+            _ => return false,
+        };
+        self.update_repetition();
+        true
+    }
+
+    pub fn get_ko_rule(&self) -> u8 {
+        self.ko_rule as u8
+    }
+
+    // Zobrist hash of the stones on the board (side to move not included)
+    fn board_hash(&self) -> u64 {
+        let mut hash = 0;
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                match self.board[y][x] {
+                    StoneState::Black => hash ^= ZOBRIST_KEYS[y * MAX_BOARD_SIZE + x][0],
+                    StoneState::White => hash ^= ZOBRIST_KEYS[y * MAX_BOARD_SIZE + x][1],
+                    StoneState::Empty => {}
+                }
+            }
+        }
+        hash
+    }
+
+    // Earliest move index at which the current position already occurred, or -1 if it
+    // is new. With include_side_to_move, the player to move must match as well.
+    pub fn first_occurrence_index(&self, include_side_to_move: bool) -> i32 {
+        let (current_hash, current_side) = self.hash_history[self.move_index];
+        self.hash_history[..self.move_index]
+            .iter()
+            .position(|&(hash, side)| hash == current_hash && (!include_side_to_move || side == current_side))
+            .map_or(-1, |index| index as i32)
+    }
+
+    // Store on move `index`, just applied at (x, y), what it did: its captures, whether it
+    // took a ko and whether it left an adjacent opponent group in atari
+    fn record_stone_effects(&mut self, index: usize, x: usize, y: usize, captured: Vec<(usize, usize)>) {
+        let opponent = opponent_of(self.board[y][x]);
+        let atari = self
+            .board
+            .neighbors(x, y)
+            .iter()
+            .any(|&(adj_x, adj_y)| self.board[adj_y][adj_x] == opponent && self.board.group_liberties(adj_x, adj_y).len() == 1);
+
+        let mv = &mut self.move_sequence[index];
+        mv.captured = captured;
+        mv.took_ko = self.board.ko.is_some();
+        mv.atari = atari;
+    }
+
+    // Put a stone on the board and remove any opponent groups it leaves without liberties,
+    // updating move numbers, the last action and capture counts. Both live play and state
+    // reconstruction go through here, so replayed games always match the original.
+    fn apply_stone(&mut self, x: usize, y: usize, player: StoneState, move_number: u32) -> Vec<(usize, usize)> {
+        self.move_numbers[y][x] = move_number;
+        self.last_action = LastAction::Stone(x, y);
+
+        let captured_stones = self.board.play(x, y, player);
+        for &(cap_x, cap_y) in &captured_stones {
+            self.move_numbers[cap_y][cap_x] = 0; // Clear move number when captured
+        }
+
+        // Update capture count
+        match player {
+            StoneState::Black => self.black_captures += captured_stones.len() as u32,
+            StoneState::White => self.white_captures += captured_stones.len() as u32,
+            StoneState::Empty => {}
+        }
+
+        captured_stones
+    }
+
+    // The point the player to move may not play on because of ko, or None
+    pub fn get_ko_point(&self) -> Option<Point> {
+        match self.board.ko {
+            Some((x, y, forbidden)) if forbidden == self.current_player => Some(Point { x: x as u32, y: y as u32 }),
+            _ => None,
+        }
+    }
+
+    // Replay the moves played so far from the setup position with full legality checks and
+    // compare the result with the live board, capture counts and player to move. Returns
+    // "consistent", or where and why the replay diverges, e.g.
+    // "diverges at index 4: Invalid move: Position already occupied".
+    pub fn verify_sequence(&self) -> String {
+        // A copy keeps every rules setting (ko rule, repetition limit, free handicap, play
+        // area, capture go); only the history, callbacks and modes that block moves go
+        let mut scratch = self.clone();
+        scratch.on_move_callback = None;
+        scratch.on_capture_callback = None;
+        scratch.on_turn_change_callback = None;
+        scratch.on_game_over_callback = None;
+        scratch.guess_mode = false;
+        scratch.review = None;
+        scratch.score_accepted_at = None;
+        scratch.move_sequence.clear();
+        scratch.move_index = 0;
+        scratch.reconstruct_state_to_index(0);
+
+        for (i, mv) in self.move_sequence[..self.move_index].iter().enumerate() {
+            let result = match (mv.x, mv.y) {
+                (Some(x), Some(y)) => scratch.place_stone_for(x, y, mv.player, mv.next_player),
+                _ if scratch.is_game_over() => MoveResult::GameOver,
+                _ => {
+                    // Appended directly: recorded histories may pass on through scoring
+                    scratch.current_player = mv.player;
+                    scratch.append_pass();
+                    scratch.current_player = mv.next_player;
+                    MoveResult::Success
+                }
+            };
+            if result != MoveResult::Success {
+                return format!("diverges at index {}: {}", i, result.message());
+            }
+        }
+
+        let end = self.move_index;
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                if scratch.board[y][x] != self.board[y][x] {
+                    return format!(
+                        "diverges at index {}: board differs at {} (replayed {:?}, live {:?})",
+                        end,
+                        self.coord_to_label(x, y),
+                        scratch.board[y][x],
+                        self.board[y][x]
+                    );
+                }
+            }
+        }
+        if (scratch.black_captures, scratch.white_captures) != (self.black_captures, self.white_captures) {
+            return format!(
+                "diverges at index {}: captures differ (replayed {}/{}, live {}/{})",
+                end, scratch.black_captures, scratch.white_captures, self.black_captures, self.white_captures
+            );
+        }
+        if scratch.current_player != self.current_player {
+            return format!(
+                "diverges at index {}: player to move differs (replayed {:?}, live {:?})",
+                end, scratch.current_player, self.current_player
+            );
+        }
+
+        "consistent".to_string()
+    }
+
+    // Development self-check: recompute derived state from scratch and compare it with the
+    // values maintained as moves are played. Returns "OK" or a "; "-separated list of problems.
+    // Never modifies the game. Checks that:
+    // - capture counts match a replay and the captures recorded on the moves played
+    // - the hash history has one entry per move index and the last one matches the board
+    // - the player to move is the one the last move (or the setup) handed the turn to
+    // - the ko point is empty
+    // - unless the board was edited mid-game: every stone's move number names a move of
+    //   that color at that point (or a setup stone for 0), empty points carry no number,
+    //   and no group is left without liberties
+    pub fn check_invariants(&self) -> String {
+        let problems = self.invariant_problems();
+        if problems.is_empty() {
+            "OK".to_string()
+        } else {
+            problems.join("; ")
+        }
+    }
+
+    // Run check_invariants after every move, navigation and edit, logging any problems.
+    // Off by default: each check replays the whole game, so a long game gets quadratic.
+    pub fn set_debug_checks(&mut self, enabled: bool) {
+        self.debug_checks = enabled;
+    }
+
+    pub fn get_debug_checks(&self) -> bool {
+        self.debug_checks
+    }
+
+    fn run_debug_checks(&self, after: &str) {
+        if !self.debug_checks {
+            return;
+        }
+        let problems = self.invariant_problems();
+        if !problems.is_empty() {
+            console_log!("Invariant check failed after {}: {}", after, problems.join("; "));
+        }
+    }
+
+    fn invariant_problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let played = &self.move_sequence[..self.move_index];
+
+        let replayed = self.replayed();
+        if (replayed.black_captures, replayed.white_captures) != (self.black_captures, self.white_captures) {
+            problems.push(format!(
+                "captures {}/{} but replay gives {}/{}",
+                self.black_captures, self.white_captures, replayed.black_captures, replayed.white_captures
+            ));
+        }
+        let recorded = |player: StoneState| played.iter().filter(|mv| mv.player == player).map(|mv| mv.captured.len() as u32).sum::<u32>();
+        if (recorded(StoneState::Black), recorded(StoneState::White)) != (self.black_captures, self.white_captures) {
+            problems.push(format!(
+                "captures {}/{} but the moves record {}/{}",
+                self.black_captures,
+                self.white_captures,
+                recorded(StoneState::Black),
+                recorded(StoneState::White)
+            ));
+        }
+
+        if self.hash_history.len() != self.move_index + 1 {
+            problems.push(format!("hash history has {} entries for move index {}", self.hash_history.len(), self.move_index));
+        } else if self.hash_history[self.move_index].0 != self.board_hash() {
+            problems.push("stored position hash doesn't match the board".to_string());
+        }
+
+        let expected_player = played.last().map_or(self.setup_player, |mv| mv.next_player);
+        if self.current_player != expected_player {
+            problems.push(format!("{:?} to move but the history hands the turn to {:?}", self.current_player, expected_player));
+        }
+
+        if let Some((x, y, _)) = self.board.ko {
+            if self.board[y][x] != StoneState::Empty {
+                problems.push(format!("ko point {} is occupied", self.coord_to_label(x, y)));
+            }
+        }
+
+        // Edits made after moves were played aren't part of the history
+        if self.position_edited {
+            return problems;
+        }
+
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                let (stone, number) = (self.board[y][x], self.move_numbers[y][x] as usize);
+                if stone == StoneState::Empty {
+                    if number != 0 {
+                        problems.push(format!("empty point {} has move number {}", self.coord_to_label(x, y), number));
+                    }
+                } else if number == 0 {
+                    if self.setup_board[y][x] != stone {
+                        problems.push(format!("{:?} stone at {} has no move number and isn't a setup stone", stone, self.coord_to_label(x, y)));
+                    }
+                } else if played.get(number - 1).is_none_or(|mv| (mv.x, mv.y, mv.player) != (Some(x), Some(y), stone)) {
+                    problems.push(format!("{:?} stone at {} has move number {} but that move was played elsewhere", stone, self.coord_to_label(x, y), number));
+                }
+            }
+        }
+
+        for group in self.board.group_summaries() {
+            if group.liberties == 0 {
+                problems.push(format!("group at {} has no liberties", self.coord_to_label(group.x, group.y)));
+            }
+        }
+
+        problems
+    }
+
+    // Play `steps` random actions on a scratch game of the given size, drawn from `seed`:
+    // moves (mostly legal), passes, undo, redo, jumps and serialize round trips, running
+    // check_invariants after each. Returns "OK" or the first failing step and its problems.
+    // Deterministic for a given seed, so a failure can be replayed.
+    pub fn fuzz(seed: u32, steps: u32, board_size: usize) -> String {
+        if !is_supported_board_size(board_size) {
+            return format!("Unsupported board size {}", board_size);
+        }
+
+        let mut rng = Rng::new(seed);
+        let mut next = move |bound: usize| rng.below(bound);
+
+        let mut game = GoGame::blank(board_size);
+        for step in 0..steps {
+            let action = match next(100) {
+                0..=69 => {
+                    // A random empty point; occupied, ko and suicide attempts are part of the test
+                    for _ in 0..8 {
+                        if game.play_move(next(board_size), next(board_size)) == MoveResult::Success {
+                            break;
+                        }
+                    }
+                    "move"
+                }
+                70..=74 => {
+                    game.handle_pass();
+                    "pass"
+                }
+                75..=84 => {
+                    game.undo_n(1 + next(3));
+                    "undo"
+                }
+                85..=91 => {
+                    game.redo_n(1 + next(3));
+                    "redo"
+                }
+                92..=94 => {
+                    game.goto_move(next(game.move_sequence.len() + 1));
+                    "goto"
+                }
+                _ => {
+                    // Only played moves are serialized, so compare against the current position
+                    let mut loaded = GoGame::blank(board_size);
+                    if !loaded.deserialize_state(&game.serialize_state()) {
+                        return format!("step {} (round trip): serialized state failed to load", step);
+                    }
+                    let summary = |g: &GoGame| (g.board_hash(), g.black_captures, g.white_captures, g.current_player, g.move_index);
+                    if summary(&loaded) != summary(&game) {
+                        return format!("step {} (round trip): loaded game differs", step);
+                    }
+                    game = loaded;
+                    "round trip"
+                }
+            };
+
+            let problems = game.invariant_problems();
+            if !problems.is_empty() {
+                return format!("step {} ({}): {}", step, action, problems.join("; "));
+            }
+        }
+        "OK".to_string()
+    }
+
+    // The game replayed from the setup position through move_index. Unlike the live board
+    // and counters this can't be skewed by edit-mode changes made mid-game.
+    fn replayed(&self) -> GoGame {
+        GoGame::replay(self.board.size, self.setup_board, self.setup_player, &self.move_sequence[..self.move_index])
+    }
+
+    // Thumbnails of the whole move sequence (including undone moves) in one pass: the
+    // positions after moves 0, N, 2N, ... and the final move, each run-length encoded as
+    // (run length, state) byte pairs covering board_size * board_size points row by row, one
+    // snapshot after another. every_n = 0 is treated as 1. Doesn't touch the live game.
+    pub fn get_position_snapshots(&self, every_n: usize) -> Box<[u8]> {
+        let every_n = every_n.max(1);
+        let size = self.board.size;
+        let mut board = Board::new(size);
+        board.stones = self.setup_board;
+
+        let mut snapshots = Vec::new();
+        let mut push_snapshot = |board: &Board| {
+            let mut run: Option<(u8, StoneState)> = None;
+            for y in 0..size {
+                for x in 0..size {
+                    let state = board.stones[y][x];
+                    run = match run {
+                        Some((length, current)) if current == state && length < u8::MAX => Some((length + 1, current)),
+                        Some((length, current)) => {
+                            snapshots.extend([length, current as u8]);
+                            Some((1, state))
+                        }
+                        None => Some((1, state)),
+                    };
+                }
+            }
+            if let Some((length, current)) = run {
+                snapshots.extend([length, current as u8]);
+            }
+        };
+
+        push_snapshot(&board);
+        for (i, mv) in self.move_sequence.iter().enumerate() {
+            match (mv.x, mv.y) {
+                (Some(x), Some(y)) => {
+                    board.play(x, y, mv.player);
+                }
+                _ => board.ko = None,
+            }
+            let played = i + 1;
+            if played % every_n == 0 || played == self.move_sequence.len() {
+                push_snapshot(&board);
+            }
+        }
+        snapshots.into_boxed_slice()
+    }
+
+    // A scratch game holding the position reached by playing `moves` from a setup position
+    fn replay(board_size: usize, setup_board: [[StoneState; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], setup_player: StoneState, moves: &[Move]) -> GoGame {
+        let mut scratch = GoGame::blank(board_size);
+        scratch.setup_board = setup_board;
+        scratch.setup_player = setup_player;
+        scratch.move_sequence = moves.to_vec();
+        scratch.reconstruct_state_to_index(moves.len());
+        scratch
+    }
+
+    // Drop any undone moves beyond move_index, keeping them as a discarded branch if enabled
+    fn truncate_future(&mut self) {
+        if self.move_index >= self.move_sequence.len() {
+            return;
+        }
+
+        let discarded = self.move_sequence.split_off(self.move_index);
+        if self.preserve_branches {
+            let name = format!("Branch {} at move {}", self.discarded_branches.len() + 1, self.move_index);
+            console_log!("Preserved {} discarded moves as \"{}\"", discarded.len(), name);
+            self.discarded_branches.push(DiscardedBranch {
+                name,
+                from_index: self.move_index,
+                moves: discarded,
+            });
+        }
+    }
+
+    // When enabled, playing after an undo keeps the undone moves as a discarded branch
+    // instead of throwing them away
+    pub fn set_preserve_branches(&mut self, enabled: bool) {
+        self.preserve_branches = enabled;
+    }
+
+    // List preserved branches as JSON: [{"name","from_move","moves":[[x, y, player], ...]}]
+    // where from_move is the number of moves played before the branch and passes are [-1, -1, player]
+    pub fn list_discarded_branches(&self) -> String {
+        let branches: Vec<String> = self
+            .discarded_branches
+            .iter()
+            .map(|branch| {
+                let moves: Vec<String> = branch
+                    .moves
+                    .iter()
+                    .map(|mv| match (mv.x, mv.y) {
+                        (Some(x), Some(y)) => format!("[{},{},{}]", x, y, mv.player as u8),
+                        _ => format!("[-1,-1,{}]", mv.player as u8),
+                    })
+                    .collect();
+                format!(
+                    "{{\"name\":\"{}\",\"from_move\":{},\"moves\":[{}]}}",
+                    branch.name,
+                    branch.from_index,
+                    moves.join(",")
+                )
+            })
+            .collect();
+        format!("[{}]", branches.join(","))
+    }
+
+    // Free-placement teaching mode: clicks place the configured color without alternating
+    // turns, while captures, suicide checks and move recording still apply
+    pub fn set_free_placement(&mut self, enabled: bool) {
+        console_log!("Free placement {}", if enabled { "enabled" } else { "disabled" });
+        let was_enabled = self.free_placement;
+        self.free_placement = enabled;
+        // Entering or leaving the mode, the configured color is the one to move next;
+        // recorded in the history like any other turn change
+        if enabled || was_enabled {
+            self.change_player_to_move(self.placement_color as u8, true).ok();
+        }
+    }
+
+    pub fn is_free_placement(&self) -> bool {
+        self.free_placement
+    }
+
+    // Select the color placed in free-placement mode (1 = Black, 2 = White)
+    pub fn set_placement_color(&mut self, color: u8) -> bool {
+        self.placement_color = match color {
+            1 => StoneState::Black,
+            2 => StoneState::White,
+            _ => return false,
+        };
+        if self.free_placement {
+            self.change_player_to_move(color, true).ok();
+        }
+        true
+    }
+
+    // Choose who moves (1 = Black, 2 = White). Allowed before any moves, where it also sets
+    // the starting color that gets serialized, or in free placement mode, where turn order
+    // isn't enforced. Errors if it would contradict the recorded moves.
+    pub fn set_current_player(&mut self, color: u8) -> Result<(), JsError> {
+        self.change_player_to_move(color, false)
+    }
+
+    // Position-editing form of set_current_player: `force` also allows changing the player
+    // to move in the middle of a normal game. Returns whether the player was changed.
+    pub fn set_turn(&mut self, color: u8, force: bool) -> bool {
+        self.change_player_to_move(color, force).is_ok()
+    }
+
+    fn change_player_to_move(&mut self, color: u8, force: bool) -> Result<(), JsError> {
+        let player = match color {
+            1 => StoneState::Black,
+            2 => StoneState::White,
+            _ => return Err(JsError::new("Invalid color: expected 1 (Black) or 2 (White)")),
+        };
+
+        if self.move_sequence.is_empty() {
+            self.setup_player = player;
+            self.reconstruct_state_to_index(0);
+        } else if self.free_placement || force {
+            // Record the choice on the last played move so undo/redo and serialization keep it
+            if self.move_index > 0 {
+                self.move_sequence[self.move_index - 1].next_player = player;
+            } else {
+                self.setup_player = player;
+            }
+            self.current_player = player;
+            if let Some(entry) = self.hash_history.get_mut(self.move_index) {
+                entry.1 = player;
+            }
+        } else {
+            return Err(JsError::new("Cannot change the player to move after moves have been played"));
+        }
+
+        console_log!("Player to move set to {}", self.current_player_name());
+        self.run_debug_checks("turn change");
+        Ok(())
+    }
+
+    // Make the current position (including any edits) the setup position, so move numbering
+    // starts again from 1 with the next move. The moves played so far, their numbers and
+    // the capture counts are dropped. Refused after moves in a normal game unless `force`.
+    pub fn reset_move_numbering(&mut self, force: bool) -> bool {
+        if !self.move_sequence.is_empty() && !self.free_placement && !force {
+            return false;
+        }
+
+        self.setup_board = self.board.stones;
+        self.setup_player = self.current_player;
+        self.free_handicap = 0;
+        self.move_sequence.clear();
+        self.move_index = 0;
+        self.discarded_branches.clear();
+        self.reconstruct_state_to_index(0);
+        console_log!("Move numbering restarted from the current position");
+        true
+    }
+
+    // Restrict play to the inclusive rectangle (x0, y0)-(x1, y1). Stones outside the
+    // rectangle still count for captures and liberties; they just can't be played on.
+    pub fn set_play_area(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) -> bool {
+        let (min_x, max_x) = (x0.min(x1), x0.max(x1));
+        let (min_y, max_y) = (y0.min(y1), y0.max(y1));
+        if max_x >= self.board.size || max_y >= self.board.size {
+            return false;
+        }
+
+        self.play_area = Some((min_x, min_y, max_x, max_y));
+        console_log!("Play area set to ({}, {})-({}, {})", min_x, min_y, max_x, max_y);
+        true
+    }
+
+    // Get the active play area as [x0, y0, x1, y1] (returns None if the whole board is playable)
+    pub fn get_play_area(&self) -> Option<Box<[u32]>> {
+        self.play_area
+            .map(|(x0, y0, x1, y1)| vec![x0 as u32, y0 as u32, x1 as u32, y1 as u32].into_boxed_slice())
+    }
+
+    pub fn clear_play_area(&mut self) {
+        self.play_area = None;
+    }
+
+    // Opt in to including the play area in serialized state, for sharing problems
+    pub fn set_serialize_play_area(&mut self, enabled: bool) {
+        self.serialize_play_area = enabled;
+    }
+
+    // Turn off the integrity checksum. Such states carry version 0 in the header and load
+    // without verification; the rest of the encoding is unchanged, so builds that predate
+    // the checksum can't necessarily read them.
+    pub fn set_serialize_checksum(&mut self, enabled: bool) {
+        self.serialize_checksum = enabled;
+    }
+
+    fn is_in_play_area(&self, x: usize, y: usize) -> bool {
+        match self.play_area {
+            Some((x0, y0, x1, y1)) => x >= x0 && x <= x1 && y >= y0 && y <= y1,
+            None => true,
+        }
+    }
+
+    // Jump directly to the position after `index` moves (0 = the setup position).
+    // Indices past the end of the stored sequence are clamped to the last move.
+    pub fn goto_move(&mut self, index: usize) -> bool {
+        self.stop_autoplay(); // Manual navigation pauses autoplay
+        self.move_index = index.min(self.move_sequence.len());
+        self.reconstruct_state_to_index(self.move_index);
+        console_log!("Goto: moved to move index {}", self.move_index);
+        true
+    }
+
+    // Net change from the current position to the one after `target` moves (clamped like
+    // goto_move), forward or backward, without moving there: JSON {"target", "added":
+    // [[x, y, color], ...], "removed": [[x, y, color], ...], "current_player"}. A point
+    // that changes color appears in both lists.
+    pub fn diff_to_move(&self, target: usize) -> String {
+        let target = target.min(self.move_sequence.len());
+        let replayed = GoGame::replay(self.board.size, self.setup_board, self.setup_player, &self.move_sequence[..target]);
+
+        let mut added = Vec::new();
+        let mut removed = Vec::new();
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                let (before, after) = (self.board[y][x], replayed.board[y][x]);
+                if before == after {
+                    continue;
+                }
+                if before != StoneState::Empty {
+                    removed.push(format!("[{},{},{}]", x, y, before as u8));
+                }
+                if after != StoneState::Empty {
+                    added.push(format!("[{},{},{}]", x, y, after as u8));
+                }
+            }
+        }
+
+        format!(
+            "{{\"target\":{},\"added\":[{}],\"removed\":[{}],\"current_player\":{}}}",
+            target,
+            added.join(","),
+            removed.join(","),
+            replayed.current_player as u8
+        )
+    }
+
+    // Review during a live game: saves the live position so undo/redo/goto (and autoplay)
+    // can wander freely, while playing, passing and editing are rejected with ReviewActive.
+    // A game clock should stay paused while is_reviewing() is true. False if already reviewing.
+    pub fn enter_review(&mut self) -> bool {
+        if self.review.is_some() {
+            return false;
+        }
+        self.review = Some(ReviewSnapshot {
+            board: self.board.clone(),
+            move_numbers: self.move_numbers,
+            current_player: self.current_player,
+            move_sequence: self.move_sequence.clone(),
+            move_index: self.move_index,
+            black_captures: self.black_captures,
+            white_captures: self.white_captures,
+            last_action: self.last_action,
+            position_edited: self.position_edited,
+            dead_stones: self.dead_stones.clone(),
+            hash_history: self.hash_history.clone(),
+            discarded_branches: self.discarded_branches.clone(),
+        });
+        console_log!("Entered review at move index {}", self.move_index);
+        true
+    }
+
+    // Leave review, discarding everything done while reviewing and restoring the live
+    // position exactly as it was. False if not reviewing.
+    pub fn exit_review(&mut self) -> bool {
+        let Some(live) = self.review.take() else { return false };
+        let previous_player = self.current_player;
+        self.stop_autoplay();
+        self.board = live.board;
+        self.move_numbers = live.move_numbers;
+        self.current_player = live.current_player;
+        self.move_sequence = live.move_sequence;
+        self.move_index = live.move_index;
+        self.black_captures = live.black_captures;
+        self.white_captures = live.white_captures;
+        self.last_action = live.last_action;
+        self.position_edited = live.position_edited;
+        self.dead_stones = live.dead_stones;
+        self.hash_history = live.hash_history;
+        self.discarded_branches = live.discarded_branches;
+        self.update_repetition();
+        console_log!("Left review, back at move index {}", self.move_index);
+        if self.current_player != previous_player {
+            self.notify_turn_change();
+        }
+        true
+    }
+
+    pub fn is_reviewing(&self) -> bool {
+        self.review.is_some()
+    }
+
+    // Start guess-the-move training at the position after `from_move` moves of the loaded game
+    pub fn start_guess_mode(&mut self, from_move: usize) -> bool {
+        if from_move >= self.move_sequence.len() {
+            return false;
+        }
+        self.goto_move(from_move);
+        self.guess_mode = true;
+        self.guess_total = 0;
+        self.guess_correct = 0;
+        self.guess_streak = 0;
+        self.guess_best_streak = 0;
+        true
+    }
+
+    pub fn stop_guess_mode(&mut self) {
+        self.guess_mode = false;
+    }
+
+    pub fn is_guess_mode(&self) -> bool {
+        self.guess_mode
+    }
+
+    // Compare a guess with the game's next move and advance to it. Returns JSON
+    // {"correct","actual_x","actual_y","distance","streak"}, where actual_x/actual_y and
+    // distance are -1 if the game move was a pass; "null" if there is nothing to guess.
+    // The move sequence itself is never modified.
+    pub fn submit_guess(&mut self, x: usize, y: usize) -> String {
+        if !self.guess_mode || self.move_index >= self.move_sequence.len() {
+            return "null".to_string();
+        }
+
+        let actual = self.move_sequence[self.move_index].clone();
+        let (correct, actual_x, actual_y, distance) = match (actual.x, actual.y) {
+            (Some(ax), Some(ay)) => (
+                ax == x && ay == y,
+                ax as i32,
+                ay as i32,
+                (ax.abs_diff(x) + ay.abs_diff(y)) as i32,
+            ),
+            _ => (false, -1, -1, -1),
+        };
+
+        self.guess_total += 1;
+        if correct {
+            self.guess_correct += 1;
+            self.guess_streak += 1;
+            self.guess_best_streak = self.guess_best_streak.max(self.guess_streak);
+        } else {
+            self.guess_streak = 0;
+        }
+
+        // Advance to the actual game move
+        self.goto_move(self.move_index + 1);
+
+        format!(
+            "{{\"correct\":{},\"actual_x\":{},\"actual_y\":{},\"distance\":{},\"streak\":{}}}",
+            correct, actual_x, actual_y, distance, self.guess_streak
+        )
+    }
+
+    // Guessing statistics as JSON: {"guesses","correct","streak","best_streak","remaining"}
+    pub fn guess_stats(&self) -> String {
+        format!(
+            "{{\"guesses\":{},\"correct\":{},\"streak\":{},\"best_streak\":{},\"remaining\":{}}}",
+            self.guess_total,
+            self.guess_correct,
+            self.guess_streak,
+            self.guess_best_streak,
+            self.move_sequence.len() - self.move_index
+        )
+    }
+
+    pub fn undo(&mut self) -> bool {
+        self.stop_autoplay(); // Manual navigation pauses autoplay
+        if self.can_undo() {
+            self.move_index -= 1;
+            self.reconstruct_state_to_index(self.move_index);
+            console_log!("Undo: moved to move index {}", self.move_index);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Undo and describe what changed for animation: [x, y, player] of the undone move
+    // (x = y = -1 for a pass) followed by an [x, y, color] triple for every captured stone
+    // that reappears. Returns an empty slice if there is nothing to undo.
+    pub fn undo_detailed(&mut self) -> Box<[i32]> {
+        if !self.can_undo() {
+            return Box::new([]);
+        }
+
+        let undone = self.move_sequence[self.move_index - 1].clone();
+        let board_before = self.board.stones;
+        self.undo();
+
+        let mut details = match (undone.x, undone.y) {
+            (Some(x), Some(y)) => vec![x as i32, y as i32, undone.player as i32],
+            _ => vec![-1, -1, undone.player as i32],
+        };
+
+        // Stones present now but not before the undo are restored captures
+        for (y, row) in board_before.iter().enumerate().take(self.board.size) {
+            for (x, &before) in row.iter().enumerate().take(self.board.size) {
+                if before == StoneState::Empty && self.board[y][x] != StoneState::Empty {
+                    details.extend_from_slice(&[x as i32, y as i32, self.board[y][x] as i32]);
+                }
+            }
+        }
+
+        details.into_boxed_slice()
+    }
+
+    pub fn redo(&mut self) -> bool {
+        self.stop_autoplay(); // Manual navigation pauses autoplay
+        self.step_forward()
+    }
+
+    // Redo one move without touching autoplay
+    fn step_forward(&mut self) -> bool {
+        if self.can_redo() {
+            self.move_index += 1;
+            self.reconstruct_state_to_index(self.move_index);
+            console_log!("Redo: moved to move index {}", self.move_index);
+            true
+        } else {
+            false
+        }
+    }
+
+    // Start replaying the remaining moves, one every `ms_per_move` milliseconds. The embedder
+    // drives the clock by calling autoplay_tick (e.g. from setInterval or
+    // requestAnimationFrame), so every step goes through the exported object; calling this
+    // while already playing only changes the speed. Manual navigation (undo, redo, goto and
+    // their variants) pauses it. False if there is nothing to replay.
+    pub fn start_autoplay(&mut self, ms_per_move: u32) -> bool {
+        if !self.can_redo() {
+            return false;
+        }
+        if self.autoplay_interval.is_none() {
+            self.autoplay_last_step = None;
+        }
+        self.autoplay_interval = Some(ms_per_move.max(1));
+        true
+    }
+
+    pub fn stop_autoplay(&mut self) {
+        self.autoplay_interval = None;
+    }
+
+    // Change the autoplay interval; takes effect from the next tick
+    pub fn set_autoplay_speed(&mut self, ms_per_move: u32) {
+        if self.autoplay_interval.is_some() {
+            self.autoplay_interval = Some(ms_per_move.max(1));
+        }
+    }
+
+    pub fn is_autoplaying(&self) -> bool {
+        self.autoplay_interval.is_some()
+    }
+
+    // Advance autoplay to the timestamp `now_ms` (any monotonic clock, such as
+    // performance.now()). Plays at most one move per call, firing the usual move, capture
+    // and turn-change callbacks, and stops at the end of the game. Returns whether autoplay
+    // is still running.
+    pub fn autoplay_tick(&mut self, now_ms: f64) -> bool {
+        let interval = match self.autoplay_interval {
+            Some(interval) => interval as f64,
+            None => return false,
+        };
+
+        match self.autoplay_last_step {
+            // The first tick only starts the clock
+            None => self.autoplay_last_step = Some(now_ms),
+            Some(last_step) if now_ms - last_step >= interval => {
+                self.autoplay_last_step = Some(now_ms);
+                if self.step_forward() {
+                    let mv = self.move_sequence[self.move_index - 1].clone();
+                    let (x, y) = match (mv.x, mv.y) {
+                        (Some(x), Some(y)) => (x as i32, y as i32),
+                        _ => (-1, -1),
+                    };
+                    self.notify_move(x, y, mv.player);
+                    if !mv.captured.is_empty() {
+                        self.notify_capture(mv.player, &mv.captured);
+                    }
+                }
+            }
+            Some(_) => {}
+        }
+
+        if !self.can_redo() {
+            self.autoplay_interval = None;
+        }
+        self.autoplay_interval.is_some()
+    }
+
+    // Jump to the start of the game (the setup position, if any); returns the new move index
+    pub fn undo_all(&mut self) -> usize {
+        self.goto_move(0);
+        self.move_index
+    }
+
+    // Jump to the end of the stored move sequence; returns the new move index
+    pub fn redo_all(&mut self) -> usize {
+        self.goto_move(self.move_sequence.len());
+        self.move_index
+    }
+
+    // Step back up to n moves with a single reconstruction; returns the steps actually taken
+    pub fn undo_n(&mut self, n: usize) -> usize {
+        self.stop_autoplay(); // Manual navigation pauses autoplay, even with nothing to undo
+        let steps = n.min(self.move_index);
+        if steps > 0 {
+            self.goto_move(self.move_index - steps);
+        }
+        steps
+    }
+
+    // Step forward up to n moves with a single reconstruction; returns the steps actually taken
+    pub fn redo_n(&mut self, n: usize) -> usize {
+        self.stop_autoplay(); // Manual navigation pauses autoplay, even with nothing to redo
+        let steps = n.min(self.move_sequence.len() - self.move_index);
+        if steps > 0 {
+            self.goto_move(self.move_index + steps);
+        }
+        steps
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.move_index > 0
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.move_index < self.move_sequence.len()
+    }
+
+    // Read-only JS properties mirroring the accessor methods above
+    #[wasm_bindgen(getter = boardSize)]
+    pub fn board_size(&self) -> usize {
+        self.board.size
+    }
+
+    #[wasm_bindgen(getter = currentPlayer)]
+    pub fn current_player(&self) -> u8 {
+        self.get_current_player()
+    }
+
+    // Player to move as "black" or "white"
+    #[wasm_bindgen(getter = currentPlayerName)]
+    pub fn current_player_name(&self) -> String {
+        match self.current_player {
+            StoneState::White => "white".to_string(),
+            _ => "black".to_string(),
+        }
+    }
+
+    #[wasm_bindgen(getter = blackCaptures)]
+    pub fn black_captures(&self) -> u32 {
+        self.black_captures
+    }
+
+    #[wasm_bindgen(getter = whiteCaptures)]
+    pub fn white_captures(&self) -> u32 {
+        self.white_captures
+    }
+
+    #[wasm_bindgen(getter = moveCount)]
+    pub fn move_count(&self) -> usize {
+        self.move_sequence.len()
+    }
+
+    #[wasm_bindgen(getter = moveIndex)]
+    pub fn move_index(&self) -> usize {
+        self.move_index
+    }
+
+    #[wasm_bindgen(getter = canUndo)]
+    pub fn undo_available(&self) -> bool {
+        self.can_undo()
+    }
+
+    #[wasm_bindgen(getter = canRedo)]
+    pub fn redo_available(&self) -> bool {
+        self.can_redo()
+    }
+
+    // Number of moves currently played (the position in the move sequence)
+    pub fn get_move_index(&self) -> usize {
+        self.move_index
+    }
+
+    // Total number of stored moves, including undone moves that can still be redone
+    pub fn get_total_moves(&self) -> usize {
+        self.move_sequence.len()
+    }
+
+    pub fn has_future_moves(&self) -> bool {
+        self.future_move_count() > 0
+    }
+
+    // Number of redo moves that playing a new move here would discard
+    pub fn future_move_count(&self) -> usize {
+        self.move_sequence.len() - self.move_index
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        self.canvas_width = width;
+        self.canvas_height = height;
+    }
+
+    // Show or hide move numbers on stones drawn by render()
+    pub fn set_show_move_numbers(&mut self, show: bool) {
+        self.show_move_numbers = show;
+    }
+
+    // Set the colors render() uses (any CSS color strings). Malformed colors are ignored
+    // and keep their previous value; returns false if any color was rejected.
+    pub fn set_theme(&mut self, board_color: &str, line_color: &str, black_stone: &str, white_stone: &str, marker_color: &str) -> bool {
+        let mut all_valid = true;
+        let updates = [
+            (&mut self.theme.board, board_color),
+            (&mut self.theme.line, line_color),
+            (&mut self.theme.black_stone, black_stone),
+            (&mut self.theme.white_stone, white_stone),
+            (&mut self.theme.marker, marker_color),
+        ];
+        for (slot, color) in updates {
+            if is_css_color(color) {
+                *slot = color.trim().to_string();
+            } else {
+                console_log!("Ignoring malformed theme color \"{}\"", color);
+                all_valid = false;
+            }
+        }
+        all_valid
+    }
+
+    // Resize for a display with the given devicePixelRatio: the canvas backing store becomes
+    // CSS size x ratio so render() draws at full device resolution, while layout and click
+    // conversion stay in CSS pixels
+    pub fn resize_with_dpi(&mut self, css_width: u32, css_height: u32, device_pixel_ratio: f64) {
+        let ratio = if device_pixel_ratio.is_finite() && device_pixel_ratio > 0.0 { device_pixel_ratio } else { 1.0 };
+        self.canvas_width = css_width;
+        self.canvas_height = css_height;
+        self.device_pixel_ratio = ratio;
+        if let Some(canvas) = &self.canvas {
+            canvas.set_width((css_width as f64 * ratio).round() as u32);
+            canvas.set_height((css_height as f64 * ratio).round() as u32);
+        }
+    }
+
+    // Convert a position in CSS pixels relative to the canvas to the nearest intersection,
+    // using the same layout as render(); None outside the board
+    pub fn canvas_to_board(&self, css_x: f64, css_y: f64) -> Option<Point> {
+        let (cell, offset_x, offset_y) = self.board_layout(self.canvas_width as f64, self.canvas_height as f64);
+        if cell <= 0.0 {
+            return None;
+        }
+        let x = ((css_x - offset_x) / cell).round();
+        let y = ((css_y - offset_y) / cell).round();
+        let max = (self.board.size - 1) as f64;
+        if (0.0..=max).contains(&x) && (0.0..=max).contains(&y) {
+            Some(Point { x: x as u32, y: y as u32 })
+        } else {
+            None
+        }
+    }
+
+    // Draw the board, star points, stones, move numbers and last-move marker onto the
+    // canvas the game was created with. Layout is in CSS pixels, scaled by the device pixel
+    // ratio from resize_with_dpi so lines and stones stay sharp on high-DPI screens.
+    pub fn render(&self) -> Result<(), JsValue> {
+        let (canvas, ctx) = match self.render_target()? {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+
+        let ratio = self.device_pixel_ratio;
+        let width = canvas.width() as f64 / ratio;
+        let height = canvas.height() as f64 / ratio;
+        let (cell, offset_x, offset_y) = self.board_layout(width, height);
+        let board_pixels = cell * (self.board.size - 1) as f64;
+        let snap = |v: f64| ((v * ratio).round() + 0.5) / ratio; // Align to device pixel centers for crisp lines
+
+        // Scale CSS pixels to device pixels, replacing any transform the page applied
+        ctx.set_transform(ratio, 0.0, 0.0, ratio, 0.0, 0.0)?;
+
+        // Board background
+        ctx.set_fill_style_str(&self.theme.board);
+        ctx.fill_rect(0.0, 0.0, width, height);
+
+        // Grid lines
+        ctx.set_stroke_style_str(&self.theme.line);
+        ctx.set_line_width((cell * 0.03).max(1.0));
+        for i in 0..self.board.size {
+            let pos = i as f64 * cell;
+            ctx.begin_path();
+            ctx.move_to(snap(offset_x), snap(offset_y + pos));
+            ctx.line_to(snap(offset_x + board_pixels), snap(offset_y + pos));
+            ctx.move_to(snap(offset_x + pos), snap(offset_y));
+            ctx.line_to(snap(offset_x + pos), snap(offset_y + board_pixels));
+            ctx.stroke();
+        }
+
+        // Star points
+        ctx.set_fill_style_str(&self.theme.line);
+        for (sx, sy) in self.star_points() {
+            ctx.begin_path();
+            ctx.arc(snap(offset_x + sx as f64 * cell), snap(offset_y + sy as f64 * cell), (cell * 0.08).max(2.0), 0.0, std::f64::consts::TAU)?;
+            ctx.fill();
+        }
+
+        // Stones and move numbers
+        let radius = cell * 0.4;
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                let state = self.displayed_stone(x, y);
+                if state == 0 {
+                    continue;
+                }
+                let cx = snap(offset_x + x as f64 * cell);
+                let cy = snap(offset_y + y as f64 * cell);
+
+                // Subtle shadow offset down and right
+                ctx.begin_path();
+                ctx.arc(cx + radius * 0.15, cy + radius * 0.15, radius, 0.0, std::f64::consts::TAU)?;
+                ctx.set_fill_style_str("rgba(0, 0, 0, 0.15)");
+                ctx.fill();
+
+                // Stone in the theme color, lit from the top-left by a radial highlight
+                let (stone_color, text_color) = if state == 1 {
+                    (&self.theme.black_stone, &self.theme.white_stone)
+                } else {
+                    (&self.theme.white_stone, &self.theme.black_stone)
+                };
+                ctx.begin_path();
+                ctx.arc(cx, cy, radius, 0.0, std::f64::consts::TAU)?;
+                ctx.set_fill_style_str(stone_color);
+                ctx.fill();
+                let gradient = ctx.create_radial_gradient(cx - radius * 0.3, cy - radius * 0.3, 0.0, cx, cy, radius)?;
+                gradient.add_color_stop(0.0, "rgba(255, 255, 255, 0.35)")?;
+                gradient.add_color_stop(1.0, "rgba(255, 255, 255, 0)")?;
+                ctx.set_fill_style_canvas_gradient(&gradient);
+                ctx.fill();
+                if state == 2 {
+                    ctx.set_stroke_style_str(&self.theme.line);
+                    ctx.set_line_width((cell * 0.05).max(1.0));
+                    ctx.stroke();
+                }
+
+                let move_number = self.get_move_number(x, y);
+                if self.show_move_numbers && move_number > 0 {
+                    ctx.set_font(&format!("bold {}px Arial", (cell * 0.25).max(8.0)));
+                    ctx.set_text_align("center");
+                    ctx.set_text_baseline("middle");
+                    ctx.set_fill_style_str(text_color);
+                    ctx.fill_text(&move_number.to_string(), cx, cy)?;
+                }
+            }
+        }
+
+        // Last-move marker
+        if let Some((x, y)) = self.last_stone() {
+            ctx.begin_path();
+            ctx.arc(snap(offset_x + x as f64 * cell), snap(offset_y + y as f64 * cell), cell * 0.3, 0.0, std::f64::consts::TAU)?;
+            ctx.set_stroke_style_str(&self.theme.marker);
+            ctx.set_line_width((cell * 0.08).max(2.0));
+            ctx.stroke();
+        }
+
+        Ok(())
+    }
+
+    // Render the board with a semi-transparent ghost stone of the color that would be placed
+    // at (x, y), as a placement preview. Illegal, occupied and off-board points get no ghost.
+    pub fn render_hover(&self, x: usize, y: usize) -> Result<(), JsValue> {
+        self.render()?;
+
+        let stone = self.stone_to_place();
+        if self.check_move(x, y, stone) != MoveResult::Success {
+            return Ok(());
+        }
+        let (canvas, ctx) = match self.render_target()? {
+            Some(target) => target,
+            None => return Ok(()),
+        };
+
+        let ratio = self.device_pixel_ratio;
+        let (cell, offset_x, offset_y) = self.board_layout(canvas.width() as f64 / ratio, canvas.height() as f64 / ratio);
+        let cx = (((offset_x + x as f64 * cell) * ratio).round() + 0.5) / ratio;
+        let cy = (((offset_y + y as f64 * cell) * ratio).round() + 0.5) / ratio;
+
+        ctx.set_global_alpha(0.5);
+        ctx.begin_path();
+        ctx.arc(cx, cy, cell * 0.4, 0.0, std::f64::consts::TAU)?;
+        ctx.set_fill_style_str(if stone == StoneState::Black { &self.theme.black_stone } else { &self.theme.white_stone });
+        ctx.fill();
+        if stone == StoneState::White {
+            ctx.set_stroke_style_str(&self.theme.line);
+            ctx.set_line_width((cell * 0.05).max(1.0));
+            ctx.stroke();
+        }
+        ctx.set_global_alpha(1.0);
+
+        Ok(())
+    }
+
+    // The attached canvas and its 2D context, or None for games without a canvas
+    fn render_target(&self) -> Result<Option<(HtmlCanvasElement, CanvasRenderingContext2d)>, JsValue> {
+        let canvas = match &self.canvas {
+            Some(canvas) => canvas.clone(),
+            None => return Ok(None),
+        };
+        let ctx = match canvas.get_context("2d")? {
+            Some(ctx) => ctx.dyn_into::<CanvasRenderingContext2d>()?,
+            None => return Ok(None),
+        };
+        Ok(Some((canvas, ctx)))
+    }
+
+    // Cell size and the pixel offset of the top-left intersection for a drawing area,
+    // leaving a one-cell margin around the grid (matches the page's layout)
+    fn board_layout(&self, width: f64, height: f64) -> (f64, f64, f64) {
+        let cell = width.min(height) / (self.board.size + 1) as f64;
+        let board_pixels = cell * (self.board.size - 1) as f64;
+        (cell, (width - board_pixels) / 2.0, (height - board_pixels) / 2.0)
+    }
+
+    // Shift the whole game by (dx, dy): setup stones, every stored move (including undone
+    // moves and preserved branches) and the play area. Rejected without changing anything if
+    // a stone would leave the board, or if the shift would change the outcome of any move
+    // (a group moved away from the edge no longer being captured, for instance).
+    pub fn translate_position(&mut self, dx: i32, dy: i32) -> bool {
+        let size = self.board.size as i32;
+        let shift = |x: usize, y: usize| -> Option<(usize, usize)> {
+            let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+            ((0..size).contains(&nx) && (0..size).contains(&ny)).then_some((nx as usize, ny as usize))
+        };
+        let shift_moves = |moves: &[Move]| -> Option<Vec<Move>> {
+            moves
+                .iter()
+                .map(|mv| match (mv.x, mv.y) {
+                    (Some(x), Some(y)) => shift(x, y).map(|(nx, ny)| Move { x: Some(nx), y: Some(ny), ..mv.clone() }),
+                    _ => Some(mv.clone()),
+                })
+                .collect()
+        };
+
+        let mut setup_board = [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                if self.setup_board[y][x] != StoneState::Empty {
+                    let Some((nx, ny)) = shift(x, y) else { return false };
+                    setup_board[ny][nx] = self.setup_board[y][x];
+                }
+            }
+        }
+        let Some(move_sequence) = shift_moves(&self.move_sequence) else { return false };
+        let Some(branches) = self
+            .discarded_branches
+            .iter()
+            .map(|branch| shift_moves(&branch.moves).map(|moves| DiscardedBranch { moves, ..branch.clone() }))
+            .collect::<Option<Vec<_>>>()
+        else {
+            return false;
+        };
+
+        // Every move must capture the same (shifted) stones as before
+        let before = GoGame::replay(self.board.size, self.setup_board, self.setup_player, &self.move_sequence);
+        let after = GoGame::replay(self.board.size, setup_board, self.setup_player, &move_sequence);
+        let same_captures = before.move_sequence.iter().zip(&after.move_sequence).all(|(old, new)| {
+            let mut shifted: Vec<(usize, usize)> = old.captured.iter().filter_map(|&(x, y)| shift(x, y)).collect();
+            let mut captured = new.captured.clone();
+            shifted.sort();
+            captured.sort();
+            shifted.len() == old.captured.len() && shifted == captured
+        });
+        if !same_captures {
+            return false;
+        }
+
+        self.setup_board = setup_board;
+        self.move_sequence = move_sequence;
+        self.discarded_branches = branches;
+        self.play_area = self.play_area.and_then(|(x0, y0, x1, y1)| {
+            let ((nx0, ny0), (nx1, ny1)) = (shift(x0, y0)?, shift(x1, y1)?);
+            Some((nx0, ny0, nx1, ny1))
+        });
+        self.reconstruct_state_to_index(self.move_index);
+        console_log!("Translated position by ({}, {})", dx, dy);
+        true
+    }
+
+    // Bounding box of the visible stones as [min_x, min_y, max_x, max_y], for cropping
+    // diagrams to the active area; [-1, -1, -1, -1] on an empty board
+    pub fn get_stone_bounds(&self) -> Box<[i32]> {
+        let mut bounds: Option<[i32; 4]> = None;
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                if self.displayed_stone(x, y) == 0 {
+                    continue;
+                }
+                let (x, y) = (x as i32, y as i32);
+                bounds = Some(match bounds {
+                    None => [x, y, x, y],
+                    Some([x0, y0, x1, y1]) => [x0.min(x), y0.min(y), x1.max(x), y1.max(y)],
+                });
+            }
+        }
+        Box::new(bounds.unwrap_or([-1; 4]))
+    }
+
+    // Star points (hoshi) for the current board size as flattened [x0, y0, x1, y1, ...]
+    pub fn get_star_points(&self) -> Box<[u32]> {
+        self.star_points().into_iter().flat_map(|(x, y)| [x as u32, y as u32]).collect()
+    }
+
+    // Star points as a row-major board-sized grid: 1 at a star point, 0 elsewhere
+    pub fn get_star_point_grid(&self) -> Box<[u8]> {
+        let mut grid = vec![0u8; self.board.size * self.board.size];
+        for (x, y) in self.star_points() {
+            grid[y * self.board.size + x] = 1;
+        }
+        grid.into_boxed_slice()
+    }
+
+    // Conventional star point (hoshi) positions for the current board size: just the center
+    // on 7x7, corners and center up to 15x15, and all nine on 17x17 and 19x19
+    fn star_points(&self) -> Vec<(usize, usize)> {
+        let edge = if self.board.size >= 13 { 3 } else { 2 };
+        let (lo, mid, hi) = (edge, self.board.size / 2, self.board.size - 1 - edge);
+        if self.board.size < 9 {
+            vec![(mid, mid)]
+        } else if self.board.size >= 17 {
+            [lo, mid, hi].iter().flat_map(|&y| [(lo, y), (mid, y), (hi, y)]).collect()
+        } else {
+            vec![(lo, lo), (hi, lo), (mid, mid), (lo, hi), (hi, hi)]
+        }
+    }
+
+    pub fn get_black_captures(&self) -> u32 {
+        self.black_captures
+    }
+
+    pub fn get_white_captures(&self) -> u32 {
+        self.white_captures
+    }
+
+    // Get the last move position (returns None if no move has been made or the last move was a pass)
+    pub fn get_last_move(&self) -> Option<Point> {
+        self.last_stone().map(|(x, y)| Point { x: x as u32, y: y as u32 })
+    }
+
+    // Allocation-free get_last_move for per-frame use: (x << 16) | y, or 0xFFFFFFFF if there
+    // is no last move
+    pub fn get_last_move_packed(&self) -> u32 {
+        self.last_stone().map_or(u32::MAX, |(x, y)| ((x as u32) << 16) | y as u32)
+    }
+
+    // Deprecated: get_last_move as a [x, y] array
+    pub fn get_last_move_array(&self) -> Option<Box<[u32]>> {
+        self.last_stone().map(|(x, y)| vec![x as u32, y as u32].into_boxed_slice())
+    }
+
+    // Describe the last action: a stone placement, or a pass (isPass, x = y = -1) so the UI
+    // can tell "White passed" apart from a fresh game. Returns None before any move.
+    pub fn get_last_action(&self) -> Option<MoveInfo> {
+        let last_move = self.move_index.checked_sub(1).map(|i| &self.move_sequence[i]);
+        match self.last_action {
+            LastAction::None => None,
+            LastAction::Stone(x, y) if self.is_blind_hidden(x, y) => None,
+            LastAction::Stone(x, y) => Some(MoveInfo {
+                x: x as i32,
+                y: y as i32,
+                player: self.displayed_stone(x, y),
+                is_pass: false,
+                move_number: self.move_index as u32,
+                captured_count: last_move.map_or(0, |mv| mv.captured.len() as u32),
+                was_ko_capture: last_move.is_some_and(|mv| mv.took_ko),
+                put_opponent_in_atari: last_move.is_some_and(|mv| mv.atari),
+            }),
+            LastAction::Pass(player) => Some(MoveInfo {
+                x: -1,
+                y: -1,
+                player: self.displayed_player(player),
+                is_pass: true,
+                move_number: self.move_index as u32,
+                captured_count: 0,
+                was_ko_capture: false,
+                put_opponent_in_atari: false,
+            }),
+        }
+    }
+
+    // Screen-reader narration of the last action, e.g. "Black played Q16, capturing 3 white
+    // stones" or "White passed". Stones hidden by blindfold mode are announced without a
+    // position ("Black played"). None before any move.
+    pub fn get_last_action_description(&self) -> Option<String> {
+        let mv = self.move_sequence[..self.move_index].last()?;
+        let player = color_word(self.displayed_player(mv.player));
+        let (x, y) = match self.last_action {
+            LastAction::None => return None,
+            LastAction::Pass(_) => return Some(format!("{} passed", player)),
+            LastAction::Stone(x, y) => (x, y),
+        };
+        if self.is_blind_hidden(x, y) {
+            return Some(format!("{} played", player));
+        }
+
+        let mut description = format!("{} played {}", player, self.coord_to_label(x, y));
+        if !mv.captured.is_empty() {
+            let captured = color_word(self.displayed_player(opponent_of(mv.player))).to_lowercase();
+            let stones = if mv.captured.len() == 1 { "stone" } else { "stones" };
+            description.push_str(&format!(", capturing {} {} {}", mv.captured.len(), captured, stones));
+        }
+        Some(description)
+    }
+
+    // Screen-reader description of a point: "White stone at D4, part of a 6-stone group
+    // with 2 liberties", "Empty point at K10, legal for Black" (or "illegal for Black"), or
+    // "Hidden point at C3" under blindfold mode. Empty for off-board points.
+    pub fn describe_position(&self, x: usize, y: usize) -> String {
+        if x >= self.board.size || y >= self.board.size {
+            return String::new();
+        }
+
+        let label = self.coord_to_label(x, y);
+        if self.is_blind_hidden(x, y) {
+            return format!("Hidden point at {}", label);
+        }
+        if self.board[y][x] == StoneState::Empty {
+            let player = self.stone_to_place();
+            let legality = if self.check_move(x, y, player) == MoveResult::Success { "legal" } else { "illegal" };
+            return format!("Empty point at {}, {} for {}", label, legality, color_word(self.displayed_player(player)));
+        }
+
+        let mut group = Vec::new();
+        self.board.find_group_stones(x, y, self.board[y][x], &mut group);
+        let liberties = self.board.group_liberties(x, y).len();
+        format!(
+            "{} stone at {}, part of a {}-stone group with {} {}",
+            color_word(self.displayed_stone(x, y)),
+            label,
+            group.len(),
+            liberties,
+            if liberties == 1 { "liberty" } else { "liberties" }
+        )
+    }
+
+    // Position of the last stone placed, unless it was followed by a pass or is hidden
+    fn last_stone(&self) -> Option<(usize, usize)> {
+        match self.last_action {
+            LastAction::Stone(x, y) if !self.is_blind_hidden(x, y) => Some((x, y)),
+            _ => None,
+        }
+    }
+
+    // Moves played so far, in order. Presentation filters apply: in one-color mode every
+    // player is reported as black and blindfold mode omits the hidden recent moves.
+    pub fn get_move_history(&self) -> Vec<MoveInfo> {
+        let hide_after = if self.blind_moves == 0 || self.reveal {
+            self.move_index
+        } else {
+            self.move_index.saturating_sub(self.blind_moves).max(self.blind_start)
+        };
+
+        self.move_sequence[..hide_after]
+            .iter()
+            .enumerate()
+            .map(|(i, mv)| self.move_info(mv, i + 1))
+            .collect()
+    }
+
+    // The move redo would play next (a pass has isPass set and x = y = -1), without applying
+    // it; None at the end of the current line. Its capture, ko and atari flags are those
+    // from the last time it was played through.
+    pub fn peek_next_move(&self) -> Option<MoveInfo> {
+        self.move_sequence.get(self.move_index).map(|mv| self.move_info(mv, self.move_index + 1))
+    }
+
+    // A recorded move as seen by JS, with the effects stored when it was played or replayed
+    fn move_info(&self, mv: &Move, move_number: usize) -> MoveInfo {
+        MoveInfo {
+            x: mv.x.map_or(-1, |x| x as i32),
+            y: mv.y.map_or(-1, |y| y as i32),
+            player: self.displayed_player(mv.player),
+            is_pass: mv.x.is_none(),
+            move_number: move_number as u32,
+            captured_count: mv.captured.len() as u32,
+            was_ko_capture: mv.took_ko,
+            put_opponent_in_atari: mv.atari,
+        }
+    }
+
+    // Prisoners taken from `color` (1 = Black, 2 = White) as a chronological JSON array of
+    // {"x", "y", "move_number", "dead"}: stones captured in play (with the capturing move's
+    // number), then stones of that color marked dead for scoring ("dead": true, move_number 0)
+    pub fn get_prisoners(&self, color: u8) -> String {
+        let color = match color {
+            1 => StoneState::Black,
+            2 => StoneState::White,
+            _ => return "[]".to_string(),
+        };
+
+        let mut prisoners = Vec::new();
+        for (i, mv) in self.move_sequence[..self.move_index].iter().enumerate() {
+            if opponent_of(mv.player) == color {
+                for &(x, y) in &mv.captured {
+                    prisoners.push(format!("{{\"x\":{},\"y\":{},\"move_number\":{},\"dead\":false}}", x, y, i + 1));
+                }
+            }
+        }
+        for &(x, y) in &self.dead_stones {
+            if self.board[y][x] == color {
+                prisoners.push(format!("{{\"x\":{},\"y\":{},\"move_number\":0,\"dead\":true}}", x, y));
+            }
+        }
+        format!("[{}]", prisoners.join(","))
+    }
+
+    // Scoring: mark the group at (x, y) dead, or alive again if it was marked. Marks are
+    // cleared whenever the position changes. Groups in seki are alive and can't be marked.
+    // Returns whether the group is now marked dead.
+    pub fn toggle_dead_group(&mut self, x: usize, y: usize) -> bool {
+        if x >= self.board.size || y >= self.board.size || self.board[y][x] == StoneState::Empty {
+            return false;
+        }
+        if !self.dead_stones.contains(&(x, y)) && self.in_seki(x, y) {
+            return false;
+        }
+
+        let mut group = Vec::new();
+        self.board.find_group_stones(x, y, self.board[y][x], &mut group);
+        if self.dead_stones.contains(&(x, y)) {
+            self.dead_stones.retain(|stone| !group.contains(stone));
+            false
+        } else {
+            self.dead_stones.extend(group);
+            true
+        }
+    }
+
+    pub fn is_dead_stone(&self, x: usize, y: usize) -> bool {
+        self.dead_stones.contains(&(x, y))
+    }
+
+    pub fn clear_dead_stones(&mut self) {
+        self.dead_stones.clear();
+    }
+
+    // Dame (neutral points) as flattened [x0, y0, x1, y1, ...]: empty points next to both a
+    // black and a white stone that isn't marked dead
+    pub fn get_dame_points(&self) -> Box<[u32]> {
+        self.dame_points().into_iter().flat_map(|(x, y)| [x as u32, y as u32]).collect()
+    }
+
+    fn dame_points(&self) -> Vec<(usize, usize)> {
+        let mut dame = Vec::new();
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                if self.board[y][x] != StoneState::Empty {
+                    continue;
+                }
+                let (mut black, mut white) = (false, false);
+                for &(adj_x, adj_y) in self.board.neighbors(x, y) {
+                    if self.dead_stones.contains(&(adj_x, adj_y)) {
+                        continue;
+                    }
+                    match self.board[adj_y][adj_x] {
+                        StoneState::Black => black = true,
+                        StoneState::White => white = true,
+                        StoneState::Empty => {}
+                    }
+                }
+                if black && white {
+                    dame.push((x, y));
+                }
+            }
+        }
+        dame
+    }
+
+    // Scoring phase: the last two moves were passes
+    fn in_scoring_phase(&self) -> bool {
+        self.get_consecutive_passes() >= 2
+    }
+
+    // Number of passes in a row ending at the current move (e.g. 1 = one more pass ends play)
+    pub fn get_consecutive_passes(&self) -> u32 {
+        self.move_sequence[..self.move_index].iter().rev().take_while(|mv| mv.x.is_none()).count() as u32
+    }
+
+    // Current phase (see GamePhase: 0 = playing, 1 = scoring, 2 = finished). Accepting the
+    // score is tied to the move it was accepted at, so undoing past it resumes scoring.
+    pub fn get_game_phase(&self) -> u8 {
+        self.game_phase() as u8
+    }
+
+    fn game_phase(&self) -> GamePhase {
+        if self.is_game_over() {
+            GamePhase::Finished
+        } else if self.in_scoring_phase() {
+            GamePhase::Scoring
+        } else {
+            GamePhase::Playing
+        }
+    }
+
+    // Whether passing during scoring accepts the current dead-stone marking (the default)
+    // rather than being rejected
+    pub fn set_pass_accepts_score(&mut self, enabled: bool) {
+        self.pass_accepts_score = enabled;
+    }
+
+    pub fn get_pass_accepts_score(&self) -> bool {
+        self.pass_accepts_score
+    }
+
+    // During the scoring phase, fill the dame alternately for both players (starting with the
+    // player to move) as real moves, skipping points that would put the filler in atari.
+    // A player with nothing left to fill is skipped. The filling moves are followed by a pass
+    // from each player, which returns the game to the scoring phase, and dead-stone marks
+    // survive. Returns how many points were filled; 0 outside the scoring phase.
+    pub fn fill_dame(&mut self) -> usize {
+        if !self.in_scoring_phase() {
+            return 0;
+        }
+
+        let dead_stones = std::mem::take(&mut self.dead_stones);
+        let mut filler = self.current_player;
+        let mut filled = 0;
+        loop {
+            self.dead_stones = dead_stones.clone();
+            let fill = [filler, opponent_of(filler)].into_iter().find_map(|color| {
+                self.dame_points()
+                    .into_iter()
+                    .find(|&(x, y)| self.check_move(x, y, color) == MoveResult::Success && !self.is_self_atari(x, y, color))
+                    .map(|point| (point, color))
+            });
+            let Some(((x, y), color)) = fill else { break };
+
+            filler = opponent_of(color);
+            self.place_stone_for(x, y, color, filler);
+            filled += 1;
+        }
+
+        if filled > 0 {
+            self.append_pass();
+            self.append_pass();
+        }
+        self.dead_stones = dead_stones;
+        filled
+    }
+
+    // Whether a stone of `color` at empty (x, y) would be left with a single liberty. Captures
+    // are ignored, which only errs on the side of caution.
+    fn is_self_atari(&mut self, x: usize, y: usize, color: StoneState) -> bool {
+        self.board[y][x] = color;
+        let liberties = self.board.group_liberties(x, y).len();
+        self.board[y][x] = StoneState::Empty;
+        liberties < 2
+    }
+
+    // Colors of the passes played so far, in order (1 = Black, 2 = White), for rulesets that
+    // count passes as prisoners. These are the real colors even in one-color mode, since the
+    // prisoner count depends on who passed.
+    pub fn get_pass_moves(&self) -> Box<[u8]> {
+        self.move_sequence[..self.move_index]
+            .iter()
+            .filter(|mv| mv.x.is_none())
+            .map(|mv| mv.player as u8)
+            .collect()
+    }
+
+    // Stones captured by the move at sequence index `index` (0-based), for per-move capture
+    // counts in the move list. Empty for passes, non-capturing moves and out-of-range indices.
+    pub fn get_captures_at_move(&self, index: usize) -> Vec<Point> {
+        self.move_sequence
+            .get(index)
+            .map(|mv| mv.captured.iter().map(|&(x, y)| Point { x: x as u32, y: y as u32 }).collect())
+            .unwrap_or_default()
+    }
+
+    // Alias of get_captures_at_move; every stored move, undone or not, records its captures
+    pub fn get_captures_at(&self, index: usize) -> Vec<Point> {
+        self.get_captures_at_move(index)
+    }
+
+    // Annotate the move at sequence index `index` (0-based); false if there is no such move
+    pub fn set_move_quality(&mut self, index: usize, quality: MoveQuality) -> bool {
+        match self.move_sequence.get_mut(index) {
+            Some(mv) => {
+                mv.quality = quality;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Annotate the move just played (0 = None, 1 = Good/TE, 2 = Bad/BM, 3 = Interesting/IT,
+    // 4 = Doubtful/DO); false before the first move or for an unknown value
+    pub fn set_current_move_quality(&mut self, quality: u8) -> bool {
+        match (self.move_index.checked_sub(1), MoveQuality::from_code(quality)) {
+            (Some(index), Some(quality)) => self.set_move_quality(index, quality),
+            _ => false,
+        }
+    }
+
+    // Annotation of the move at sequence index `index` (None for unannotated or missing moves)
+    pub fn get_move_quality(&self, index: usize) -> MoveQuality {
+        self.move_sequence.get(index).map_or(MoveQuality::None, |mv| mv.quality)
+    }
+
+    // Every annotated move in the sequence as a JSON array of
+    // {"index", "quality", "symbol"} for badging the move list
+    pub fn get_annotated_moves(&self) -> String {
+        let moves: Vec<String> = self
+            .move_sequence
+            .iter()
+            .enumerate()
+            .filter(|(_, mv)| mv.quality != MoveQuality::None)
+            .map(|(index, mv)| {
+                format!("{{\"index\":{},\"quality\":{},\"symbol\":\"{}\"}}", index, mv.quality as u8, mv.quality.symbol())
+            })
+            .collect();
+        format!("[{}]", moves.join(","))
+    }
+
+    // Moves played since the most recent capture by either side (all played moves if there
+    // has never been a capture). Low values indicate an active fight.
+    pub fn moves_since_capture(&self) -> u32 {
+        self.move_sequence[..self.move_index]
+            .iter()
+            .rev()
+            .take_while(|mv| mv.captured.is_empty())
+            .count() as u32
+    }
+
+    // Number of stones captured by each played move, in order
+    pub fn get_capture_counts(&self) -> Box<[u32]> {
+        self.move_sequence[..self.move_index]
+            .iter()
+            .map(|mv| mv.captured.len() as u32)
+            .collect()
+    }
+
+    // Handle pass move - player passes their turn
+    pub fn handle_pass(&mut self) -> String {
+        if self.guess_mode {
+            return MoveResult::GuessModeActive.message().to_string();
+        }
+
+        if self.review.is_some() {
+            return MoveResult::ReviewActive.message().to_string();
+        }
+
+        match self.game_phase() {
+            GamePhase::Finished => return MoveResult::GameOver.message().to_string(),
+            GamePhase::Scoring if self.pass_accepts_score => {
+                self.score_accepted_at = Some(self.move_index);
+                console_log!("Score accepted with {} dead stones", self.dead_stones.len());
+                self.notify_game_over();
+                return "Score accepted".to_string();
+            }
+            GamePhase::Scoring => return MoveResult::ScoringPhase.message().to_string(),
+            GamePhase::Playing => {}
+        }
+
+        self.append_pass();
+        if self.in_scoring_phase() {
+            console_log!("Two consecutive passes: scoring");
+        }
+        self.notify_game_over();
+
+        "Pass successful".to_string()
+    }
+
+    // Record a pass by the player to move and hand the turn over
+    fn append_pass(&mut self) {
+        console_log!("Player {} passes", match self.current_player {
+            StoneState::Black => "Black",
+            StoneState::White => "White",
+            StoneState::Empty => "Empty",
+        });
+
+        // Remove any future moves if we're not at the end (truncate for new branch)
+        self.truncate_future();
+
+        // Add pass move to sequence
+        let passing_player = self.current_player;
+
+        // Switch players
+        self.current_player = match self.current_player {
+            StoneState::Black => StoneState::White,
+            StoneState::White => StoneState::Black,
+            StoneState::Empty => StoneState::Black,
+        };
+
+        self.move_sequence.push(Move {
+            x: None,
+            y: None,
+            player: passing_player,
+            next_player: self.current_player,
+            captured: Vec::new(),
+            took_ko: false,
+            atari: false,
+            quality: MoveQuality::None,
+        });
+        self.move_index += 1;
+
+        // Record that the last action was a pass, which also lifts any ko
+        self.last_action = LastAction::Pass(passing_player);
+        self.board.ko = None;
+        self.record_position();
+
+        self.notify_move(-1, -1, passing_player);
+        self.notify_turn_change();
+    }
+
+    // Serialize current game state to a compact string format (base64 of serialize_bytes)
+    pub fn serialize_state(&self) -> String {
+        base64_encode(&self.serialize_bytes())
+    }
+
+    // Serialize current game state to compact bytes (a Uint8Array in JS), for storage and
+    // transfers that don't need a text-safe string
+    pub fn serialize_bytes(&self) -> Vec<u8> {
+        let mut state_bytes = Vec::new();
+
+        // Pack board size (3-bit code, see board_size_from_code) and current player (2 bits) into 1 byte
+        let board_size_code = (0..8).find(|&code| board_size_from_code(code) == Some(self.board.size)).unwrap_or(2);
+        let player_code = match self.current_player {
+            StoneState::Empty => 0u8,
+            StoneState::Black => 1u8,
+            StoneState::White => 2u8,
+        };
+        // Format version in the top 3 bits (see FORMAT_VERSION); 0 marks a state without a checksum
+        let version = if self.serialize_checksum { FORMAT_VERSION } else { 0 };
+        let header_byte = (version << 5) | (board_size_code << 2) | player_code;
+        state_bytes.push(header_byte);
+
+        // Variable-length encoding for capture counts (saves space for small numbers).
+        // Captures are recomputed from the moves so identical games serialize identically.
+        let replayed = self.replayed();
+        encode_varint(&mut state_bytes, replayed.black_captures);
+        encode_varint(&mut state_bytes, replayed.white_captures);
+
+        // Encode move sequence up to current move_index
+        encode_varint(&mut state_bytes, self.move_index as u32);
+        let moves = &self.move_sequence[..self.move_index];
+        let mut i = 0;
+        while i < moves.len() {
+            let mv = &moves[i];
+            i += 1;
+            match (mv.x, mv.y) {
+                (Some(x), Some(y)) => {
+                    // Stone placement: encode position (9 bits for 19x19) + player (2 bits)
+                    let position = (y * self.board.size + x) as u16;
+                    let player_bits = match mv.player {
+                        StoneState::Black => 1u16,
+                        StoneState::White => 2u16,
+                        StoneState::Empty => 0u16,
+                    };
+                    let encoded = (position << 2) | player_bits;
+                    // Store as 2 bytes (little endian)
+                    state_bytes.push(encoded as u8);
+                    state_bytes.push((encoded >> 8) as u8);
+                }
+                (None, None) => {
+                    // From version 2, consecutive passes alternating in color share one word
+                    // (see PASS_RUN)
+                    let mut run = 1;
+                    while version >= 2 && i < moves.len() && run < MAX_PASS_RUN
+                        && moves[i].x.is_none()
+                        && moves[i].player == opponent_of(moves[i - 1].player)
+                    {
+                        run += 1;
+                        i += 1;
+                    }
+
+                    // A single pass: 0xFFFC | player bits (0xFFFD Black, 0xFFFE White), far
+                    // above any stone encoding. Older links used 0xFFFF with the color implied.
+                    let encoded = match run {
+                        1 => 0xFFFC | mv.player as u16,
+                        _ => PASS_RUN | ((run as u16) << 2) | mv.player as u16,
+                    };
+                    state_bytes.push(encoded as u8);
+                    state_bytes.push((encoded >> 8) as u8);
+                }
+                (None, Some(_)) | (Some(_), None) => {
+                    // Invalid move data - this should never happen in a properly constructed move sequence
+                    console_log!("Warning: Invalid move data encountered during serialization");
+                }
+            }
+        }
+
+        // Optional tagged extension records follow the move list
+        if self.has_setup() {
+            let mut payload = vec![self.setup_player as u8];
+            for y in 0..self.board.size {
+                for x in 0..self.board.size {
+                    if self.setup_board[y][x] != StoneState::Empty {
+                        let encoded = (((y * self.board.size + x) as u16) << 2) | self.setup_board[y][x] as u16;
+                        payload.push(encoded as u8);
+                        payload.push((encoded >> 8) as u8);
+                    }
+                }
+            }
+            encode_extension(&mut state_bytes, EXT_SETUP, &payload);
+        }
+        if self.serialize_play_area {
+            if let Some((x0, y0, x1, y1)) = self.play_area {
+                encode_extension(&mut state_bytes, EXT_PLAY_AREA, &[x0 as u8, y0 as u8, x1 as u8, y1 as u8]);
+            }
+        }
+        if let Some(game_match) = &self.game_match {
+            encode_extension(&mut state_bytes, EXT_MATCH, &game_match.encode());
+        }
+        // Only annotations of moves that are written; undone moves aren't part of the state
+        let annotations: Vec<u8> = moves
+            .iter()
+            .enumerate()
+            .filter(|(_, mv)| mv.quality != MoveQuality::None)
+            .flat_map(|(index, mv)| {
+                let mut record = Vec::new();
+                encode_varint(&mut record, index as u32);
+                record.push(mv.quality as u8);
+                record
+            })
+            .collect();
+        if !annotations.is_empty() {
+            encode_extension(&mut state_bytes, EXT_ANNOTATIONS, &annotations);
+        }
+        if self.capture_go_target > 0 {
+            let mut payload = Vec::new();
+            encode_varint(&mut payload, self.capture_go_target);
+            encode_extension(&mut state_bytes, EXT_CAPTURE_GO, &payload);
+        }
+        if self.free_handicap > 0 {
+            // Manual free handicap still being placed (its stones are the first moves)
+            encode_extension(&mut state_bytes, EXT_FREE_HANDICAP, &[self.free_handicap as u8]);
+        }
+        if self.serialize_checksum {
+            // Low 32 bits of the final position's Zobrist hash, to catch corrupted links
+            encode_extension(&mut state_bytes, EXT_CHECKSUM, &(replayed.board_hash() as u32).to_le_bytes());
+        }
+
+        state_bytes
+    }
+
+    // Restore game state from a serialized string
+    pub fn deserialize_state(&mut self, state_str: &str) -> bool {
+        base64_decode(state_str).is_some_and(|state_bytes| self.deserialize_bytes(&state_bytes))
+    }
+
+    // Restore game state from the raw bytes produced by serialize_bytes
+    pub fn deserialize_bytes(&mut self, state_bytes: &[u8]) -> bool {
+        let loaded = self.decode_state_bytes(state_bytes);
+        if !loaded {
+            console_log!("Rejected serialized state of {} bytes", state_bytes.len());
+        }
+        loaded
+    }
+
+    fn decode_state_bytes(&mut self, state_bytes: &[u8]) -> bool {
+        if state_bytes.is_empty() {
+            return false;
+        }
+
+        let mut idx = 0;
+
+        // Decode header byte
+        let header_byte = state_bytes[idx];
+        idx += 1;
+
+        let version = header_byte >> 5;
+        if version > FORMAT_VERSION {
+            return false;
+        }
+
+        let board_size = match board_size_from_code((header_byte >> 2) & 0b111) {
+            Some(size) => size,
+            None => return false,
+        };
+
+        let player_code = header_byte & 0b11;
+        let current_player = match player_code {
+            0 => StoneState::Empty,
+            1 => StoneState::Black,
+            2 => StoneState::White,
+            _ => return false,
+        };
+
+        // Decode variable-length capture counts (for validation)
+        if let Some((_black_captures, new_idx)) = decode_varint(state_bytes, idx) {
+            idx = new_idx;
+            if let Some((_white_captures, new_idx)) = decode_varint(state_bytes, idx) {
+                idx = new_idx;
+
+                // Decode move count
+                if let Some((move_count, new_idx)) = decode_varint(state_bytes, idx) {
+                    idx = new_idx;
+
+                    // Decode move sequence
+                    let mut move_sequence = Vec::new();
+                    while move_sequence.len() < move_count as usize {
+                        if idx + 1 >= state_bytes.len() {
+                            return false;
+                        }
+
+                        let encoded = state_bytes[idx] as u16 | ((state_bytes[idx + 1] as u16) << 8);
+                        idx += 2;
+
+                        if encoded >= 0xFFFC {
+                            // Pass move carrying its color; legacy 0xFFFF passes get theirs
+                            // from the surrounding moves once the starting color is known
+                            let player = match encoded & 0b11 {
+                                1 => StoneState::Black,
+                                2 => StoneState::White,
+                                3 => StoneState::Empty,
+                                _ => return false,
+                            };
+                            move_sequence.push(Move {
+                                x: None,
+                                y: None,
+                                player,
+                                next_player: opponent_of(player),
+                                captured: Vec::new(),
+                                took_ko: false,
+                                atari: false,
+                                quality: MoveQuality::None,
+                            });
+                        } else if version >= 2 && encoded >= PASS_RUN {
+                            // Run of passes alternating in color from the first one
+                            let run = ((encoded >> 2) & 0x3FF) as usize;
+                            let mut player = match encoded & 0b11 {
+                                1 => StoneState::Black,
+                                2 => StoneState::White,
+                                _ => return false,
+                            };
+                            if run < 2 || move_sequence.len() + run > move_count as usize {
+                                return false;
+                            }
+                            for _ in 0..run {
+                                move_sequence.push(Move {
+                                    x: None,
+                                    y: None,
+                                    player,
+                                    next_player: opponent_of(player),
+                                    captured: Vec::new(),
+                                    took_ko: false,
+                                    atari: false,
+                                    quality: MoveQuality::None,
+                                });
+                                player = opponent_of(player);
+                            }
+                        } else {
+                            // Stone placement
+                            let position = (encoded >> 2) as usize;
+                            let player_bits = encoded & 0b11;
+                            let player = match player_bits {
+                                1 => StoneState::Black,
+                                2 => StoneState::White,
+                                _ => return false,
+                            };
+
+                            let x = position % board_size;
+                            let y = position / board_size;
+
+                            if x >= board_size || y >= board_size {
+                                return false;
+                            }
+
+                            move_sequence.push(Move {
+                                x: Some(x),
+                                y: Some(y),
+                                player,
+                                next_player: opponent_of(player),
+                                captured: Vec::new(),
+                                took_ko: false,
+                                atari: false,
+                                quality: MoveQuality::None,
+                            });
+                        }
+                    }
+
+                    // Decode optional extension records after the move list
+                    let extensions = match decode_extensions(state_bytes, idx) {
+                        Some(extensions) => extensions,
+                        None => return false,
+                    };
+
+                    // Setup stones (handicap or composed positions) are the base the moves replay from
+                    let mut setup_board = [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+                    let mut setup_player = StoneState::Black;
+                    for (tag, payload) in &extensions {
+                        if *tag == EXT_SETUP {
+                            match decode_setup(payload, board_size) {
+                                Some((board, player)) => {
+                                    setup_board = board;
+                                    setup_player = player;
+                                }
+                                None => return false,
+                            }
+                        }
+                    }
+
+                    // Legacy passes belong to the opponent of the previous mover (or the
+                    // starting player for an opening pass)
+                    for i in 0..move_sequence.len() {
+                        if move_sequence[i].player == StoneState::Empty {
+                            move_sequence[i].player = match i {
+                                0 => setup_player,
+                                _ => opponent_of(move_sequence[i - 1].player),
+                            };
+                        }
+                    }
+
+                    // Whoever moved next had the turn; after the final move the header says
+                    for i in 1..move_sequence.len() {
+                        move_sequence[i - 1].next_player = move_sequence[i].player;
+                    }
+                    if let (Some(last), StoneState::Black | StoneState::White) = (move_sequence.last_mut(), current_player) {
+                        last.next_player = current_player;
+                    }
+
+                    // Versioned states must carry a checksum matching the replayed position
+                    if version >= 1 {
+                        let checksum = match extensions.iter().find(|(tag, _)| *tag == EXT_CHECKSUM) {
+                            Some((_, payload)) if payload.len() == 4 => u32::from_le_bytes([payload[0], payload[1], payload[2], payload[3]]),
+                            _ => return false,
+                        };
+                        let replayed = GoGame::replay(board_size, setup_board, setup_player, &move_sequence);
+                        if replayed.board_hash() as u32 != checksum {
+                            console_log!("Rejected serialized state: checksum mismatch");
+                            return false;
+                        }
+                    }
+
+                    // Move annotations: (varint move index, quality) pairs
+                    if let Some((_, payload)) = extensions.iter().find(|(tag, _)| *tag == EXT_ANNOTATIONS) {
+                        let mut i = 0;
+                        while i < payload.len() {
+                            let (index, next) = match decode_varint(payload, i) {
+                                Some(decoded) => decoded,
+                                None => return false,
+                            };
+                            match (move_sequence.get_mut(index as usize), payload.get(next).and_then(|&code| MoveQuality::from_code(code))) {
+                                (Some(mv), Some(quality)) => mv.quality = quality,
+                                _ => return false,
+                            }
+                            i = next + 1;
+                        }
+                    }
+
+                    // The capture go target; states without one are standard games
+                    let capture_go_target = match extensions.iter().find(|(tag, _)| *tag == EXT_CAPTURE_GO) {
+                        Some((_, payload)) => match decode_varint(payload, 0) {
+                            Some((target, end)) if end == payload.len() => target,
+                            _ => return false,
+                        },
+                        None => 0,
+                    };
+
+                    // A manual free handicap in progress: Black's stones placed so far are
+                    // the moves, and the rest are still to come
+                    let free_handicap = match extensions.iter().find(|(tag, _)| *tag == EXT_FREE_HANDICAP) {
+                        Some((_, payload)) => match payload.as_slice() {
+                            &[count] if (2..=9).contains(&count) && move_count < count as u32 => count as usize,
+                            _ => return false,
+                        },
+                        None => 0,
+                    };
+
+                    // A match in progress travels with its current game
+                    let game_match = match extensions.iter().find(|(tag, _)| *tag == EXT_MATCH) {
+                        Some((_, payload)) => match MatchState::decode(payload) {
+                            Some(game_match) => Some(game_match),
+                            None => return false,
+                        },
+                        None => None,
+                    };
+
+                    // Update game state
+                    self.board.set_size(board_size);
+                    self.review = None; // A loaded game replaces the one being reviewed
+                    self.score_accepted_at = None;
+                    self.free_handicap = free_handicap;
+                    self.discarded_branches.clear();
+                    self.capture_go_target = capture_go_target;
+                    self.move_sequence = move_sequence;
+                    self.move_index = move_count as usize;
+                    self.setup_board = setup_board;
+                    self.setup_player = setup_player;
+                    if let Some(game_match) = &game_match {
+                        self.komi = game_match.game_setup().1;
+                    }
+                    self.game_match = game_match;
+
+                    // Reconstruct the current game state
+                    self.reconstruct_state_to_index(self.move_index);
+
+                    // The play area is session-local unless the link carries one
+                    if let Some((x1, y1, x2, y2)) = self.play_area {
+                        if x1.max(x2) >= board_size || y1.max(y2) >= board_size {
+                            self.play_area = None;
+                        }
+                    }
+                    for (tag, payload) in extensions {
+                        if tag == EXT_PLAY_AREA && payload.len() == 4 {
+                            let [x0, y0, x1, y1] = [payload[0], payload[1], payload[2], payload[3]].map(|v| v as usize);
+                            self.set_play_area(x0, y0, x1, y1);
+                        }
+                    }
+
+                    console_log!("Successfully deserialized game state with {} moves", move_count);
+                    return true;
+                }
+            }
+        }
+
+        false
+    }
+
+    // Read the header, counts and extension records of a serialized state without replaying
+    // it or touching any game; None if even the header can't be decoded
+    pub fn peek_state(state_str: &str) -> Option<StateSummary> {
+        let bytes = base64_decode(state_str)?;
+        let header_byte = *bytes.first()?;
+        let version = header_byte >> 5;
+        let board_size = board_size_from_code((header_byte >> 2) & 0b111)?;
+        let (black_captures, idx) = decode_varint(&bytes, 1)?;
+        let (white_captures, idx) = decode_varint(&bytes, idx)?;
+        let (move_count, mut idx) = decode_varint(&bytes, idx)?;
+
+        let mut summary = StateSummary {
+            version,
+            supported: version <= FORMAT_VERSION,
+            board_size: board_size as u32,
+            move_count,
+            current_player: header_byte & 0b11,
+            black_captures,
+            white_captures,
+            has_setup: false,
+            match_game: 0,
+            match_games: 0,
+        };
+        if !summary.supported {
+            return Some(summary);
+        }
+
+        // Step over the move list (a pass run stands for several moves) to the extensions
+        let mut moves = 0;
+        while moves < move_count as usize && idx + 1 < bytes.len() {
+            let encoded = bytes[idx] as u16 | ((bytes[idx + 1] as u16) << 8);
+            idx += 2;
+            moves += if version >= 2 && (PASS_RUN..0xFFFC).contains(&encoded) { ((encoded >> 2) & 0x3FF) as usize } else { 1 };
+        }
+        for (tag, payload) in decode_extensions(&bytes, idx).unwrap_or_default() {
+            match tag {
+                EXT_SETUP => summary.has_setup = payload.len() > 1,
+                EXT_MATCH => {
+                    if let Some(game_match) = MatchState::decode(&payload) {
+                        summary.match_game = game_match.current_game;
+                        summary.match_games = game_match.games;
+                    }
+                }
+                _ => {}
+            }
+        }
+        Some(summary)
+    }
+
+    // Read just the board size from a serialized state without loading it (-1 if undecodable);
+    // static, so callers can size a new game before creating it
+    pub fn peek_board_size(state_str: &str) -> i32 {
+        base64_decode(state_str)
+            .and_then(|bytes| bytes.first().copied())
+            .and_then(|header_byte| board_size_from_code((header_byte >> 2) & 0b111))
+            .map_or(-1, |size| size as i32)
+    }
+
+    // Export the setup position and the whole move sequence (including undone moves) as an
+    // SGF game record, with move annotations
+    pub fn export_sgf(&self) -> String {
+        let coord = |x: usize, y: usize| format!("[{}{}]", (b'a' + x as u8) as char, (b'a' + y as u8) as char);
+        let mut sgf = format!("(;GM[1]FF[4]CA[UTF-8]SZ[{}]", self.board.size);
+
+        for (property, color) in [("AB", StoneState::Black), ("AW", StoneState::White)] {
+            let mut stones = String::new();
+            for y in 0..self.board.size {
+                for x in 0..self.board.size {
+                    if self.setup_board[y][x] == color {
+                        stones.push_str(&coord(x, y));
+                    }
+                }
+            }
+            if !stones.is_empty() {
+                sgf.push_str(property);
+                sgf.push_str(&stones);
+            }
+        }
+        if self.setup_player == StoneState::White {
+            sgf.push_str("PL[W]");
+        }
+
+        for mv in &self.move_sequence {
+            sgf.push(';');
+            sgf.push(if mv.player == StoneState::White { 'W' } else { 'B' });
+            match (mv.x, mv.y) {
+                (Some(x), Some(y)) => sgf.push_str(&coord(x, y)),
+                _ => sgf.push_str("[]"),
+            }
+            if let Some(property) = mv.quality.sgf_property() {
+                sgf.push_str(property);
+            }
+        }
+
+        sgf.push(')');
+        sgf
+    }
+
+    // Load the main line of an SGF game record: board size, setup stones, player to move,
+    // moves and move annotations. Variations and unknown properties are ignored. Returns
+    // false (leaving the game unchanged) for malformed records or illegal moves.
+    pub fn import_sgf(&mut self, sgf: &str) -> bool {
+        let nodes = match parse_sgf_main_line(sgf) {
+            Some(nodes) if !nodes.is_empty() => nodes,
+            _ => return false,
+        };
+
+        let root = &nodes[0];
+        let board_size = match root.iter().find(|(ident, _)| ident == "SZ") {
+            Some((_, values)) => match values.first().and_then(|v| v.trim().parse::<usize>().ok()) {
+                Some(size) if is_supported_board_size(size) => size,
+                _ => return false,
+            },
+            None => MAX_BOARD_SIZE,
+        };
+        let point = |value: &str| -> Option<Option<(usize, usize)>> {
+            let bytes = value.as_bytes();
+            match bytes {
+                [] => Some(None),
+                [b't', b't'] if board_size <= 19 => Some(None), // FF[3] pass
+                [col, row] => {
+                    let (x, y) = (col.wrapping_sub(b'a') as usize, row.wrapping_sub(b'a') as usize);
+                    if x < board_size && y < board_size { Some(Some((x, y))) } else { None }
+                }
+                _ => None,
+            }
+        };
+
+        // Replay into a scratch game so illegal records are rejected before touching this one
+        let mut scratch = GoGame::blank(board_size);
+        for (ident, values) in root {
+            let color = match ident.as_str() {
+                "AB" => StoneState::Black,
+                "AW" => StoneState::White,
+                "PL" => {
+                    scratch.setup_player = if values.first().map(|v| v.trim()) == Some("W") { StoneState::White } else { StoneState::Black };
+                    continue;
+                }
+                _ => continue,
+            };
+            for value in values {
+                match point(value) {
+                    Some(Some((x, y))) => scratch.setup_board[y][x] = color,
+                    _ => return false,
+                }
+            }
+        }
+        scratch.reconstruct_state_to_index(0);
+
+        for node in &nodes {
+            let mut quality = MoveQuality::None;
+            let mut played = false;
+            for (ident, values) in node {
+                let value = values.first().map(String::as_str).unwrap_or("");
+                match ident.as_str() {
+                    "B" | "W" => {
+                        let player = if ident == "B" { StoneState::Black } else { StoneState::White };
+                        match point(value) {
+                            Some(Some((x, y))) => {
+                                if scratch.place_stone_for(x, y, player, opponent_of(player)) != MoveResult::Success {
+                                    return false;
+                                }
+                            }
+                            Some(None) => {
+                                scratch.current_player = player;
+                                scratch.append_pass();
+                            }
+                            None => return false,
+                        }
+                        played = true;
+                    }
+                    "TE" => quality = MoveQuality::Good,
+                    "BM" => quality = MoveQuality::Bad,
+                    "IT" => quality = MoveQuality::Interesting,
+                    "DO" => quality = MoveQuality::Doubtful,
+                    _ => {}
+                }
+            }
+            if played {
+                if let Some(mv) = scratch.move_sequence.last_mut() {
+                    mv.quality = quality;
+                }
+            }
+        }
+
+        // Whoever moved next had the turn
+        let mut move_sequence = scratch.move_sequence;
+        for i in 1..move_sequence.len() {
+            move_sequence[i - 1].next_player = move_sequence[i].player;
+        }
+
+        self.board.set_size(board_size);
+        self.review = None; // An imported game replaces the one being reviewed
+        self.score_accepted_at = None;
+        self.free_handicap = 0;
+        self.discarded_branches.clear();
+        self.move_index = move_sequence.len();
+        self.move_sequence = move_sequence;
+        self.setup_board = scratch.setup_board;
+        self.setup_player = scratch.setup_player;
+        self.reconstruct_state_to_index(self.move_index);
+        if let Some((x1, y1, x2, y2)) = self.play_area {
+            if x1.max(x2) >= board_size || y1.max(y2) >= board_size {
+                self.play_area = None;
+            }
+        }
+
+        console_log!("Imported SGF with {} moves", self.move_index);
+        true
+    }
+
+    // Check whether another serialized state reaches the same board position as this game,
+    // ignoring history and capture counts. Returns false if the state can't be decoded.
+    pub fn positions_equal(&self, other: &str) -> bool {
+        let mut scratch = GoGame::blank(self.board.size);
+        if !scratch.deserialize_state(other) || scratch.board.size != self.board.size {
+            return false;
+        }
+
+        (0..self.board.size).all(|y| self.board[y][..self.board.size] == scratch.board[y][..self.board.size])
+    }
+
+    // Check whether another serialized state decodes to the same game: board size, stones,
+    // player to move, captures and the moves played so far. Encoding details (format
+    // version, optional records) don't matter. False if the state can't be decoded.
+    pub fn equals_state(&self, other_serialized: &str) -> bool {
+        let mut other = GoGame::blank(self.board.size);
+        if !other.deserialize_state(other_serialized) {
+            return false;
+        }
+
+        let same_moves = self.move_index == other.move_index
+            && self.move_sequence[..self.move_index]
+                .iter()
+                .zip(&other.move_sequence[..other.move_index])
+                .all(|(a, b)| (a.x, a.y, a.player) == (b.x, b.y, b.player));
+        same_moves && self.state_differences(&other).is_empty()
+    }
+
+    // Describe how another serialized state differs from this game's current position, as
+    // JSON {"board_size", "cells": [[x, y, this, other], ...], "current_player", "captures"}
+    // where board_size, current_player and captures are [this, other] pairs when they differ
+    // and null otherwise. Cells are only compared for equal board sizes. None if undecodable.
+    pub fn diff_state(&self, other_serialized: &str) -> Option<String> {
+        let mut other = GoGame::blank(self.board.size);
+        if !other.deserialize_state(other_serialized) {
+            return None;
+        }
+
+        let differences = self.state_differences(&other);
+        let pair = |differs: bool, this: String, other: String| {
+            if differs { format!("[{},{}]", this, other) } else { "null".to_string() }
+        };
+        let cells: Vec<String> = differences
+            .cells
+            .iter()
+            .map(|&(x, y, this, other)| format!("[{},{},{},{}]", x, y, this as u8, other as u8))
+            .collect();
+        Some(format!(
+            "{{\"board_size\":{},\"cells\":[{}],\"current_player\":{},\"captures\":{}}}",
+            pair(differences.board_size, self.board.size.to_string(), other.board.size.to_string()),
+            cells.join(","),
+            pair(differences.current_player, (self.current_player as u8).to_string(), (other.current_player as u8).to_string()),
+            pair(
+                differences.captures,
+                format!("[{},{}]", self.black_captures, self.white_captures),
+                format!("[{},{}]", other.black_captures, other.white_captures)
+            )
+        ))
+    }
+
+    // Compare this game's current position with another game's
+    fn state_differences(&self, other: &GoGame) -> StateDifferences {
+        let board_size = self.board.size != other.board.size;
+        let mut cells = Vec::new();
+        if !board_size {
+            for y in 0..self.board.size {
+                for x in 0..self.board.size {
+                    if self.board[y][x] != other.board[y][x] {
+                        cells.push((x, y, self.board[y][x], other.board[y][x]));
+                    }
+                }
+            }
+        }
+
+        StateDifferences {
+            board_size,
+            cells,
+            current_player: self.current_player != other.current_player,
+            captures: (self.black_captures, self.white_captures) != (other.black_captures, other.white_captures),
+        }
+    }
+
+    // Return all buffered events (one per line) and clear the buffer. The buffer is shared by
+    // every game in the module, so this is a static (GoGame.drain_log() in JS).
+    pub fn drain_log() -> String {
+        EVENT_LOG.with(|events| events.borrow_mut().drain(..).map(|(_, message)| message).collect::<Vec<_>>().join("\n"))
+    }
+
+    // Return the buffered events without clearing them, one per line prefixed with its
+    // sequence number (e.g. "42 Undo: moved to move index 7"), for attaching to bug reports
+    // together with serialize_state
+    pub fn get_event_log() -> String {
+        EVENT_LOG.with(|events| {
+            events.borrow().iter().map(|(sequence, message)| format!("{} {}", sequence, message)).collect::<Vec<_>>().join("\n")
+        })
+    }
+
+    // Drop the buffered events; sequence numbers continue from where they were
+    pub fn clear_event_log() {
+        EVENT_LOG.with(|events| events.borrow_mut().clear());
+    }
+
+    // Enable or disable mirroring events to console.log for every game (events are still buffered)
+    pub fn set_verbose(verbose: bool) {
+        VERBOSE.with(|flag| flag.set(verbose));
+    }
+
+    pub fn is_verbose() -> bool {
+        VERBOSE.with(|flag| flag.get())
+    }
+
+    // Alias of set_verbose kept for existing callers
+    pub fn set_logging(&mut self, enabled: bool) {
+        GoGame::set_verbose(enabled);
+    }
+
+    // Check if there are any stones on the board
+    pub fn has_stones_on_board(&self) -> bool {
+        for y in 0..self.board.size {
+            for x in 0..self.board.size {
+                if self.board[y][x] != StoneState::Empty {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Predict the winner of a capturing race (semeai) between two adjacent enemy groups
+    // with the current player to move. Returns 1 if the owner of the first group wins, -1
+    // if the owner of the second group wins, and 0 for seki or anything the counting model
+    // can't decide (see semeai_result).
+    pub fn semeai_winner(&self, x1: usize, y1: usize, x2: usize, y2: usize) -> i8 {
+        self.semeai(x1, y1, x2, y2, self.current_player).map_or(0, |race| race.winner)
+    }
+
+    // The capturing race between the group at (x1, y1) and the touching enemy group at
+    // (x2, y2) with `to_move` (1 = Black, 2 = White) to play, as JSON {"first": {"color",
+    // "outside", "approach", "eyes", "effective"}, "second": {...}, "shared", "to_move", "ko",
+    // "winner": "first"/"second"/"seki"/"unclear", "margin"}. None unless the points hold two
+    // touching groups of opposite colors and to_move is a color.
+    //
+    // Counting: each group's effective liberties are its outside liberties plus the
+    // approach moves the opponent must play first (an outside liberty the opponent can't
+    // fill without self-atari costs one extra move). Against a group without an eye, a
+    // one-eyed group also counts the shared liberties. With M the mover's and O the other
+    // group's effective liberties and S the shared liberties when neither has an eye (else
+    // 0), the mover wins if M >= O + max(S - 1, 0), the other side wins if O >= M + max(S, 1), and
+    // otherwise it's seki. The margin is how far the winner's count exceeds that threshold.
+    // Ko fights are flagged, not resolved.
+    pub fn semeai_result(&self, x1: usize, y1: usize, x2: usize, y2: usize, to_move: u8) -> Option<String> {
+        let to_move = match to_move {
+            1 => StoneState::Black,
+            2 => StoneState::White,
+            _ => return None,
+        };
+        let race = self.semeai(x1, y1, x2, y2, to_move)?;
+
+        let color_name = |color: StoneState| if color == StoneState::Black { "black" } else { "white" };
+        let group_json = |color: StoneState, outside: usize, approach: usize, eyes: usize| {
+            format!(
+                "{{\"color\":\"{}\",\"outside\":{},\"approach\":{},\"eyes\":{},\"effective\":{}}}",
+                color_name(color),
+                outside,
+                approach,
+                eyes,
+                outside + approach
+            )
+        };
+        let winner = match race.winner {
+            _ if race.unclear => "unclear",
+            1 => "first",
+            -1 => "second",
+            _ => "seki",
+        };
+        Some(format!(
+            "{{\"first\":{},\"second\":{},\"shared\":{},\"to_move\":\"{}\",\"ko\":{},\"winner\":\"{}\",\"margin\":{}}}",
+            group_json(race.colors.0, race.outside.0, race.approach.0, race.eyes.0),
+            group_json(race.colors.1, race.outside.1, race.approach.1, race.eyes.1),
+            race.shared,
+            color_name(to_move),
+            race.ko,
+            winner,
+            race.margin
+        ))
+    }
+
+    fn semeai(&self, x1: usize, y1: usize, x2: usize, y2: usize, to_move: StoneState) -> Option<Semeai> {
+        if x1 >= self.board.size || y1 >= self.board.size || x2 >= self.board.size || y2 >= self.board.size {
+            return None;
+        }
+
+        let color1 = self.board[y1][x1];
+        let color2 = self.board[y2][x2];
+        if color1 == StoneState::Empty || color2 == StoneState::Empty || color1 == color2 {
+            return None;
+        }
+
+        // The two groups must be in contact for this to be a capturing race
+        let mut group1 = Vec::new();
+        self.board.find_group_stones(x1, y1, color1, &mut group1);
+        let mut group2 = Vec::new();
+        self.board.find_group_stones(x2, y2, color2, &mut group2);
+        let in_contact = group1.iter().any(|&(gx, gy)| {
+            group2.iter().any(|&(ox, oy)| gx.abs_diff(ox) + gy.abs_diff(oy) == 1)
+        });
+        if !in_contact {
+            return None;
+        }
+
+        let liberties1 = self.board.group_liberties(x1, y1);
+        let liberties2 = self.board.group_liberties(x2, y2);
+        let shared = liberties1.iter().filter(|lib| liberties2.contains(lib)).count();
+
+        // Eyes: empty regions bordered only by the group's color and made entirely of its liberties
+        let regions = self.board.empty_regions();
+        let eyes = |color: StoneState, liberties: &[(usize, usize)]| -> Vec<&EmptyRegion> {
+            regions
+                .iter()
+                .filter(|region| {
+                    let one_color = match color {
+                        StoneState::Black => !region.borders_white,
+                        _ => !region.borders_black,
+                    };
+                    one_color && region.points.iter().all(|point| liberties.contains(point))
+                })
+                .collect()
+        };
+        let eyes1 = eyes(color1, &liberties1);
+        let eyes2 = eyes(color2, &liberties2);
+
+        // Outside liberties exclude the shared ones and the eye; the eye point itself is
+        // filled last, so it counts as an ordinary liberty below
+        let outside = |liberties: &[(usize, usize)], other: &[(usize, usize)], eyes: &[&EmptyRegion]| -> Vec<(usize, usize)> {
+            liberties
+                .iter()
+                .filter(|point| !other.contains(point) && !eyes.iter().any(|eye| eye.points.contains(point)))
+                .copied()
+                .collect()
+        };
+        let outside1 = outside(&liberties1, &liberties2, &eyes1);
+        let outside2 = outside(&liberties2, &liberties1, &eyes2);
+        let mut probe = self.clone();
+        let approach1 = outside1.iter().filter(|&&(x, y)| probe.fill_is_self_atari(x, y, color2)).count();
+        let approach2 = outside2.iter().filter(|&&(x, y)| probe.fill_is_self_atari(x, y, color1)).count();
+        let eye_points = |eyes: &[&EmptyRegion]| eyes.iter().map(|eye| eye.points.len()).sum::<usize>();
+
+        let unclear = eyes1.len() + eyes2.len() > 1 || eyes1.iter().chain(&eyes2).any(|eye| eye.points.len() > 1);
+        let mut count1 = outside1.len() + approach1 + eye_points(&eyes1);
+        let mut count2 = outside2.len() + approach2 + eye_points(&eyes2);
+        // With one eye against none, the shared liberties count only for the eye group
+        let extra = if !eyes1.is_empty() {
+            count1 += shared;
+            0
+        } else if !eyes2.is_empty() {
+            count2 += shared;
+            0
+        } else {
+            shared
+        };
+
+        let (mover, other, mover_is_first) = if to_move == color1 { (count1, count2, true) } else { (count2, count1, false) };
+        let mover_needs = other + extra.saturating_sub(1);
+        let (winner, margin) = if unclear {
+            (0, 0)
+        } else if mover >= mover_needs {
+            (if mover_is_first { 1 } else { -1 }, mover - mover_needs)
+        } else if other >= mover + extra.max(1) {
+            (if mover_is_first { -1 } else { 1 }, other - (mover + extra.max(1)))
+        } else {
+            (0, 0)
+        };
+
+        Some(Semeai {
+            colors: (color1, color2),
+            liberties: (liberties1.len(), liberties2.len()),
+            outside: (outside1.len(), outside2.len()),
+            approach: (approach1, approach2),
+            eyes: (eyes1.len(), eyes2.len()),
+            shared,
+            ko: self.board.ko.is_some_and(|(kx, ky, _)| liberties1.contains(&(kx, ky)) || liberties2.contains(&(kx, ky))),
+            unclear,
+            winner,
+            margin,
+        })
+    }
+
+    // Analyze the capturing race between the group at (x1, y1) and the adjacent opposing
+    // group at (x2, y2) with the current player to move, by semeai_result's counting.
+    // Returns JSON with each group's liberties, the shared liberties, the player to move and
+    // a verdict: "first"/"second" (that group wins), "seki", "depends" (a ko decides it) or
+    // "unclear" (eye shapes the simple counting rules don't cover).
+    // Returns None unless the points hold two touching groups of opposite colors.
+    pub fn analyze_capturing_race(&self, x1: usize, y1: usize, x2: usize, y2: usize) -> Option<String> {
+        let race = self.semeai(x1, y1, x2, y2, self.current_player)?;
+        let verdict = match race.winner {
+            _ if race.unclear => "unclear",
+            _ if race.ko => "depends",
+            1 => "first",
+            -1 => "second",
+            _ => "seki",
+        };
+
+        let color_name = |color: StoneState| if color == StoneState::Black { "black" } else { "white" };
+        let group_json = |color: StoneState, liberties: usize, eyes: usize| {
+            format!(
+                "{{\"color\":\"{}\",\"liberties\":{},\"exclusive\":{},\"eyes\":{}}}",
+                color_name(color),
+                liberties,
+                liberties - race.shared,
+                eyes
+            )
+        };
+        Some(format!(
+            "{{\"first\":{},\"second\":{},\"shared\":{},\"to_move\":\"{}\",\"verdict\":\"{}\"}}",
+            group_json(race.colors.0, race.liberties.0, race.eyes.0),
+            group_json(race.colors.1, race.liberties.1, race.eyes.1),
+            race.shared,
+            color_name(self.current_player),
+            verdict
+        ))
+    }
+
+    // Whether filling the empty point (x, y) with `attacker` captures nothing and leaves the
+    // attacker's stone with at most one liberty. The board is restored afterwards.
+    fn fill_is_self_atari(&mut self, x: usize, y: usize, attacker: StoneState) -> bool {
+        let defender = opponent_of(attacker);
+        self.board[y][x] = attacker;
+        let captures = self.board.neighbors(x, y).iter().any(|&(adj_x, adj_y)| {
+            self.board[adj_y][adj_x] == defender && self.board.group_liberties(adj_x, adj_y).is_empty()
+        });
+        let self_atari = !captures && self.board.group_liberties(x, y).len() <= 1;
+        self.board[y][x] = StoneState::Empty;
+        self_atari
+    }
+
+    // Mutual damezumari: the group at (x, y) shares liberties with an adjacent opposing group
+    // and neither side can fill any liberty of the other without putting itself in atari
+    // (one-eye-vs-one-eye and eyeless seki). Such groups are alive however few eyes they have.
+    fn in_seki(&self, x: usize, y: usize) -> bool {
+        let color = self.board[y][x];
+        let opponent = opponent_of(color);
+        let mut group = Vec::new();
+        self.board.find_group_stones(x, y, color, &mut group);
+        let liberties = self.board.group_liberties(x, y);
+
+        let mut probe = self.clone();
+        let mut checked = Vec::new();
+        for &(gx, gy) in &group {
+            for &(adj_x, adj_y) in self.board.neighbors(gx, gy) {
+                if self.board[adj_y][adj_x] != opponent || checked.contains(&(adj_x, adj_y)) {
+                    continue;
+                }
+                let mut other = Vec::new();
+                self.board.find_group_stones(adj_x, adj_y, opponent, &mut other);
+                checked.extend(other);
+
+                let other_liberties = self.board.group_liberties(adj_x, adj_y);
+                if !liberties.iter().any(|lib| other_liberties.contains(lib)) {
+                    continue;
+                }
+                let stuck = liberties.iter().all(|&(lx, ly)| probe.fill_is_self_atari(lx, ly, opponent))
+                    && other_liberties.iter().all(|&(lx, ly)| probe.fill_is_self_atari(lx, ly, color));
+                if stuck {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    // Whether the group at (x, y) has a stone on the outer line of the board (a point with
+    // fewer than four neighbors); false for empty or off-board points
+    pub fn touches_edge(&self, x: usize, y: usize) -> bool {
+        if x >= self.board.size || y >= self.board.size || self.board[y][x] == StoneState::Empty {
+            return false;
+        }
+
+        let mut group = Vec::new();
+        self.board.find_group_stones(x, y, self.board[y][x], &mut group);
+        group.iter().any(|&(x, y)| self.board.neighbors(x, y).len() < 4)
+    }
+
+    // Classify the eye space of the group at (x, y) by the standard small-eye-shape table:
+    // 1 = alive (two or more eye regions, or one the opponent can't reduce to one eye),
+    // 0 = killable (a nakade shape: whoever plays its vital point first decides),
+    // -1 = dead (no eye space, or a shape too small to make two eyes). Considers the empty
+    // regions bordered only by the group's color; 0 for empty or off-board points. Groups
+    // in seki count as alive.
+    pub fn eye_space_status(&self, x: usize, y: usize) -> i8 {
+        if x >= self.board.size || y >= self.board.size || self.board[y][x] == StoneState::Empty {
+            return 0;
+        }
+
+        let color = self.board[y][x];
+        let mut group = Vec::new();
+        self.board.find_group_stones(x, y, color, &mut group);
+
+        let eye_regions: Vec<EmptyRegion> = self
+            .board
+            .empty_regions()
+            .into_iter()
+            .filter(|region| {
+                let enclosed = match color {
+                    StoneState::Black => !region.borders_white,
+                    _ => !region.borders_black,
+                };
+                enclosed
+                    && region.points.iter().any(|&(px, py)| {
+                        group.iter().any(|&(gx, gy)| gx.abs_diff(px) + gy.abs_diff(py) == 1)
+                    })
+            })
+            .collect();
+
+        match eye_regions.as_slice() {
+            _ if self.in_seki(x, y) => 1,
+            [] => -1,
+            [region] => eye_shape_status(&region.points),
+            _ => 1,
+        }
+    }
+
+    // Heuristic check that the game is ready for scoring: every group is alive by
+    // eye_space_status (two eyes or seki) or dead (no usable eye space, and only touching
+    // opponent groups that are alive), and every empty region is either surrounded by live
+    // groups of one color or consists of dame next to live groups of both. Conservative:
+    // killable shapes, open areas and unresolved fights all count as unsettled, and so does
+    // any position without a live group of each color.
+    pub fn position_settled(&self) -> bool {
+        // Life status of every stone's group (0 = not yet determined)
+        let mut status = [[0i8; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        let groups = self.board.group_summaries();
+        if groups.is_empty() {
+            return false;
+        }
+        for group in &groups {
+            let group_status = self.eye_space_status(group.x, group.y);
+            let mut stones = Vec::new();
+            self.board.find_group_stones(group.x, group.y, group.color, &mut stones);
+            for (x, y) in stones {
+                status[y][x] = group_status;
+            }
+        }
+
+        // A group without eye space is only clearly dead when everything around it lives
+        for group in &groups {
+            if status[group.y][group.x] != -1 {
+                continue;
+            }
+            let mut stones = Vec::new();
+            self.board.find_group_stones(group.x, group.y, group.color, &mut stones);
+            let opponent = opponent_of(group.color);
+            let surrounded_by_live = stones.iter().all(|&(x, y)| {
+                self.board.neighbors(x, y).iter().all(|&(adj_x, adj_y)| self.board[adj_y][adj_x] != opponent || status[adj_y][adj_x] == 1)
+            });
+            if !surrounded_by_live {
+                return false;
+            }
+        }
+        if groups.iter().any(|group| status[group.y][group.x] == 0) {
+            return false;
+        }
+        // A lone color's stones see the whole board as eye space; both sides need a live group
+        for color in [StoneState::Black, StoneState::White] {
+            if !groups.iter().any(|group| group.color == color && status[group.y][group.x] == 1) {
+                return false;
+            }
+        }
+
+        // Empty points must belong to one live color or be dame between both
+        let live_neighbors = |x: usize, y: usize| {
+            let (mut black, mut white) = (false, false);
+            for &(adj_x, adj_y) in self.board.neighbors(x, y) {
+                if status[adj_y][adj_x] == 1 {
+                    match self.board[adj_y][adj_x] {
+                        StoneState::Black => black = true,
+                        StoneState::White => white = true,
+                        StoneState::Empty => {}
+                    }
+                }
+            }
+            (black, white)
+        };
+        self.board.empty_regions().iter().all(|region| {
+            let (black, white) = region.points.iter().fold((false, false), |(black, white), &(x, y)| {
+                let (b, w) = live_neighbors(x, y);
+                (black || b, white || w)
+            });
+            match (black, white) {
+                (true, true) => region.points.iter().all(|&(x, y)| live_neighbors(x, y) == (true, true)),
+                (black, white) => black || white,
+            }
+        })
+    }
+
+    // Beginner hints for a sparse board (fewer than 10 stones): conventional big points as
+    // flattened [x0, y0, x1, y1, ...], best first. Corner points in empty corners come
+    // first (4-4, 3-4 and 3-3 on 13x13 and up; 3-3 and 3-4 on smaller boards, where the
+    // center follows the 3-3 points), then open side star points, then the center.
+    // Only legal moves are suggested; empty once the opening is over.
+    pub fn get_opening_suggestions(&self) -> Box<[u32]> {
+        const MAX_OPENING_STONES: usize = 10;
+        const MAX_SUGGESTIONS: usize = 8;
+
+        let size = self.board.size;
+        let stones = (0..size).map(|y| self.board[y][..size].iter().filter(|&&s| s != StoneState::Empty).count()).sum::<usize>();
+        if stones >= MAX_OPENING_STONES {
+            return Box::new([]);
+        }
+
+        // No stone within `radius` (Chebyshev distance) of (x, y)
+        let open_area = |x: usize, y: usize, radius: usize| {
+            (y.saturating_sub(radius)..=(y + radius).min(size - 1))
+                .all(|ny| (x.saturating_sub(radius)..=(x + radius).min(size - 1)).all(|nx| self.board[ny][nx] == StoneState::Empty))
+        };
+
+        // Offsets from the corner in priority order, mapped onto every corner whose
+        // quadrant is still empty
+        let edge = if size >= 13 { 3 } else { 2 };
+        let corner_point = |(dx, dy): (usize, usize), (right, bottom): (bool, bool)| {
+            (if right { size - 1 - dx } else { dx }, if bottom { size - 1 - dy } else { dy })
+        };
+        let corners = [(true, false), (false, true), (true, true), (false, false)];
+        let open_corners: Vec<(bool, bool)> = corners
+            .iter()
+            .copied()
+            .filter(|&corner| {
+                (0..edge + 2).all(|dy| {
+                    (0..edge + 2).all(|dx| {
+                        let (x, y) = corner_point((dx, dy), corner);
+                        self.board[y][x] == StoneState::Empty
+                    })
+                })
+            })
+            .collect();
+        let corner_offsets = |offsets: &[(usize, usize)]| -> Vec<(usize, usize)> {
+            offsets
+                .iter()
+                .flat_map(|&offset| open_corners.iter().map(move |&corner| corner_point(offset, corner)))
+                .collect()
+        };
+
+        let mid = size / 2;
+        let center = vec![(mid, mid)].into_iter().filter(|&(x, y)| open_area(x, y, 2)).collect::<Vec<_>>();
+        let mut candidates = if size >= 13 {
+            let mut candidates = corner_offsets(&[(3, 3), (2, 3), (3, 2), (2, 2)]);
+            let sides = [(edge, mid), (size - 1 - edge, mid), (mid, edge), (mid, size - 1 - edge)];
+            candidates.extend(sides.iter().copied().filter(|&(x, y)| open_area(x, y, 3)));
+            candidates.extend(center);
+            candidates
+        } else {
+            let mut candidates = corner_offsets(&[(2, 2)]);
+            candidates.extend(center);
+            candidates.extend(corner_offsets(&[(2, 3), (3, 2)]));
+            candidates
+        };
+
+        let player = self.stone_to_place();
+        let mut seen = Vec::new();
+        candidates.retain(|&point| {
+            let fresh = !seen.contains(&point);
+            seen.push(point);
+            fresh && self.check_move(point.0, point.1, player) == MoveResult::Success
+        });
+        candidates.into_iter().take(MAX_SUGGESTIONS).flat_map(|(x, y)| [x as u32, y as u32]).collect()
+    }
+
+    // Empty points where the player to move may play, as flattened [x0, y0, x1, y1, ...]
+    // in row-major order
+    pub fn get_legal_moves(&self) -> Box<[u32]> {
+        self.empty_points_where(true)
+    }
+
+    // Legality for the player to move of each point in flattened [x0, y0, x1, y1, ...], as a
+    // parallel slice of 1 (legal) or 0, so a bot can test its candidates in one call. Empty
+    // if the input has an odd length.
+    pub fn are_legal(&self, points: &[u32]) -> Box<[u8]> {
+        if !points.len().is_multiple_of(2) {
+            return Box::new([]);
         }
+        let player = self.stone_to_place();
+        points
+            .chunks_exact(2)
+            .map(|point| (self.check_move(point[0] as usize, point[1] as usize, player) == MoveResult::Success) as u8)
+            .collect()
     }
 
-    pub fn get_board_size(&self) -> usize {
-        self.board_size
+    // What a click on each point would do for the player to move, one byte per point in
+    // row-major order: 0 = legal, 1 = black stone, 2 = white stone, 3 = suicide, 4 = ko (or
+    // a superko repetition), 5 = unavailable (outside the play area, or no moves are taken:
+    // game over, review or guess mode). A point is 0 exactly when handle_board_click would
+    // play there, but the whole board is decided in one pass.
+    pub fn get_legality_map(&self) -> Box<[u8]> {
+        self.legality_map().into_boxed_slice()
     }
 
-    pub fn get_current_player(&self) -> u8 {
-        match self.current_player {
-            StoneState::Black => 1,
-            StoneState::White => 2,
-            StoneState::Empty => 0,
+    fn legality_map(&self) -> Vec<u8> {
+        let player = self.stone_to_place();
+        let size = self.board.size;
+        let blocked = self.guess_mode || self.review.is_some() || self.is_game_over();
+        let superko = matches!(self.ko_rule, KoRule::PositionalSuperko | KoRule::SituationalSuperko);
+        let placements = self.board.check_all_placements(player, self.ko_rule != KoRule::None);
+
+        let mut map = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                map.push(match self.board[y][x] {
+                    StoneState::Black => 1,
+                    StoneState::White => 2,
+                    StoneState::Empty if blocked || !self.is_in_play_area(x, y) => 5,
+                    StoneState::Empty => match placements[y * size + x] {
+                        Ok(()) if superko && self.repeats_position(x, y, player) => 4,
+                        Ok(()) => 0,
+                        Err(MoveResult::Suicide) => 3,
+                        Err(MoveResult::Ko) => 4,
+                        Err(_) => 5,
+                    },
+                });
+            }
         }
+        map
     }
 
-    pub fn get_move_number(&self, x: usize, y: usize) -> u32 {
-        if x >= self.board_size || y >= self.board_size {
-            return 0;
+    // Empty points where the player to move may not play (suicide, ko, outside the play
+    // area), as flattened [x0, y0, ...] in row-major order: the complement of get_legal_moves
+    pub fn get_illegal_points(&self) -> Box<[u32]> {
+        self.empty_points_where(false)
+    }
+
+    // Empty points whose legality for the player to move matches `legal`, flattened
+    fn empty_points_where(&self, legal: bool) -> Box<[u32]> {
+        let size = self.board.size;
+        let mut points = Vec::new();
+        for (i, &code) in self.legality_map().iter().enumerate() {
+            let empty = code != 1 && code != 2;
+            if empty && (code == 0) == legal {
+                points.extend_from_slice(&[(i % size) as u32, (i / size) as u32]);
+            }
         }
-        self.move_numbers[y][x]
+        points.into_boxed_slice()
     }
 
-    // Reconstruct game state from move sequence up to move_index
-    fn reconstruct_state_to_index(&mut self, target_index: usize) {
-        // Reset to initial state
-        self.board = [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
-        self.move_numbers = [[0u32; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
-        self.current_player = StoneState::Black;
-        self.black_captures = 0;
-        self.white_captures = 0;
-        self.last_move = None;
+    // Legal moves for the player to move that capture at least one stone, as flattened
+    // [x0, y0, x1, y1, ...] in row-major order. Ko-forbidden recaptures are excluded.
+    pub fn get_capturing_moves(&self) -> Box<[u32]> {
+        let player = self.stone_to_place();
+        let opponent = opponent_of(player);
+
+        // The last liberty of every opponent group in atari is a capturing point
+        let mut capture_points: Vec<(usize, usize)> = self
+            .board
+            .group_summaries()
+            .into_iter()
+            .filter(|group| group.color == opponent && group.liberties == 1)
+            .flat_map(|group| self.board.group_liberties(group.x, group.y))
+            .collect();
+        capture_points.sort_by_key(|&(x, y)| (y, x));
+        capture_points.dedup();
+
+        capture_points
+            .into_iter()
+            .filter(|&(x, y)| self.check_move(x, y, player) == MoveResult::Success)
+            .flat_map(|(x, y)| [x as u32, y as u32])
+            .collect()
+    }
 
-        // Collect moves to avoid borrow checker issues
-        let moves_to_replay: Vec<Move> = self.move_sequence.iter().take(target_index).cloned().collect();
+    // Match the stones in one corner (0 = top-left, 1 = top-right, 2 = bottom-left,
+    // 3 = bottom-right) against the built-in joseki dictionary, regardless of reflection
+    // and which color started. Returns a JSON array of {"name", "next": [x, y] or null,
+    // "color"} giving each matching joseki's next standard move in board coordinates and
+    // the color that plays it. An empty corner or no match gives [].
+    pub fn match_corner(&self, corner: u8) -> String {
+        if corner > 3 {
+            return "[]".to_string();
+        }
+        let (right, bottom) = (corner & 1 == 1, corner & 2 == 2);
+        let size = self.board.size;
+        let to_board = |dx: usize, dy: usize| (if right { size - 1 - dx } else { dx }, if bottom { size - 1 - dy } else { dy });
+
+        // Stones in the corner quadrant in corner-local coordinates
+        let half = size / 2;
+        let mut stones = Vec::new();
+        for dy in 0..half {
+            for dx in 0..half {
+                let (x, y) = to_board(dx, dy);
+                if self.board[y][x] != StoneState::Empty {
+                    stones.push((dx, dy, self.board[y][x]));
+                }
+            }
+        }
+        if stones.is_empty() {
+            return "[]".to_string();
+        }
+        stones.sort_by_key(|&(dx, dy, color)| (dx, dy, color as u8));
 
-        // Replay moves up to target_index
-        for (i, mv) in moves_to_replay.iter().enumerate() {
-            match (mv.x, mv.y) {
-                (Some(x), Some(y)) => {
-                    // Stone placement move
-                    self.board[y][x] = mv.player;
-                    self.move_numbers[y][x] = (i + 1) as u32;
-                    self.last_move = Some((x, y));
-
-                    // Handle captures
-                    let opponent = match mv.player {
-                        StoneState::Black => StoneState::White,
-                        StoneState::White => StoneState::Black,
-                        StoneState::Empty => StoneState::Empty,
+        let mut matches: Vec<String> = Vec::new();
+        for (name, moves) in JOSEKI {
+            let moves = joseki_moves(moves);
+            if moves.len() < stones.len() {
+                continue;
+            }
+            for reflect in [false, true] {
+                for first_color in [StoneState::Black, StoneState::White] {
+                    let place = |(dx, dy, first): (usize, usize, bool)| {
+                        let (dx, dy) = if reflect { (dy, dx) } else { (dx, dy) };
+                        (dx, dy, if first { first_color } else { opponent_of(first_color) })
                     };
-
-                    let adjacent_positions = [
-                        (x.wrapping_sub(1), y), // Left
-                        (x + 1, y),             // Right
-                        (x, y.wrapping_sub(1)), // Up
-                        (x, y + 1),             // Down
-                    ];
-
-                    let mut total_captured = 0;
-                    for (adj_x, adj_y) in adjacent_positions {
-                        if adj_x < self.board_size && adj_y < self.board_size {
-                            if self.board[adj_y][adj_x] == opponent {
-                                let captured = self.capture_group_if_no_liberties(adj_x, adj_y, opponent);
-                                total_captured += captured;
-                            }
-                        }
+                    let mut prefix: Vec<(usize, usize, StoneState)> = moves[..stones.len()].iter().map(|&mv| place(mv)).collect();
+                    prefix.sort_by_key(|&(dx, dy, color)| (dx, dy, color as u8));
+                    if prefix != stones {
+                        continue;
                     }
 
-                    // Update capture count
-                    match mv.player {
-                        StoneState::Black => self.black_captures += total_captured,
-                        StoneState::White => self.white_captures += total_captured,
-                        StoneState::Empty => {},
+                    let entry = match moves.get(stones.len()).map(|&mv| place(mv)) {
+                        Some((dx, dy, color)) => {
+                            let (x, y) = to_board(dx, dy);
+                            format!("{{\"name\":\"{}\",\"next\":[{},{}],\"color\":{}}}", name, x, y, color as u8)
+                        }
+                        None => format!("{{\"name\":\"{}\",\"next\":null,\"color\":null}}", name),
+                    };
+                    if !matches.contains(&entry) {
+                        matches.push(entry);
                     }
                 }
-                (None, None) => {
-                    // Pass move
-                    self.last_move = None;
-                }
-                (None, Some(_)) | (Some(_), None) => {
-                    // Invalid move data - this should never happen in a properly constructed move sequence
-                    console_log!("Warning: Invalid move data encountered during state reconstruction");
-                }
             }
+        }
+        format!("[{}]", matches.join(","))
+    }
 
-            // Update current player for next move
-            self.current_player = match mv.player {
-                StoneState::Black => StoneState::White,
-                StoneState::White => StoneState::Black,
-                StoneState::Empty => StoneState::Black,
-            };
+    // Whether any group of the player to move is in atari (one liberty left), for a
+    // "your group is in danger" prompt. False on an empty board.
+    pub fn current_player_in_atari(&self) -> bool {
+        self.board.group_summaries()
+            .iter()
+            .any(|group| group.color == self.current_player && group.liberties == 1)
+    }
+
+    // If the group at (x, y) is in atari, return its single remaining liberty [x, y]
+    // (the point that saves or captures it); None for empty points or groups not in atari
+    pub fn atari_escape_point(&self, x: usize, y: usize) -> Option<Box<[u32]>> {
+        if x >= self.board.size || y >= self.board.size || self.board[y][x] == StoneState::Empty {
+            return None;
+        }
+
+        match self.board.group_liberties(x, y).as_slice() {
+            [(lib_x, lib_y)] => Some(vec![*lib_x as u32, *lib_y as u32].into_boxed_slice()),
+            _ => None,
         }
     }
 
-    pub fn handle_click(&mut self, x: f32, y: f32) {
-        console_log!("Click at ({}, {})", x, y);
-        // Convert normalized coordinates (-1 to 1) to board coordinates
-        // Use rounding instead of truncation to snap to nearest intersection
-        let board_x = (((x + 1.0) / 2.0 * (self.board_size - 1) as f32) + 0.5) as usize;
-        let board_y = (((y + 1.0) / 2.0 * (self.board_size - 1) as f32) + 0.5) as usize;
-
-        if board_x < self.board_size && board_y < self.board_size {
-            if self.board[board_y][board_x] == StoneState::Empty {
-                self.board[board_y][board_x] = self.current_player;
-                self.current_player = match self.current_player {
-                    StoneState::Black => StoneState::White,
-                    StoneState::White => StoneState::Black,
-                    StoneState::Empty => StoneState::Black,
-                };
-                console_log!("Placed stone at ({}, {})", board_x, board_y);
+    // Count empty points whose ownership is still ambiguous: regions bordered by both colors
+    // (dame) and regions touching a group in atari, whose life is uncertain. A high count
+    // means the game isn't ready to be scored.
+    pub fn disputed_points(&self) -> u32 {
+        // An empty region touches a group in atari exactly when it holds that group's last liberty
+        let atari_liberties: Vec<(usize, usize)> = self
+            .board
+            .group_summaries()
+            .iter()
+            .filter(|group| group.liberties == 1)
+            .flat_map(|group| self.board.group_liberties(group.x, group.y))
+            .collect();
+
+        let mut disputed = 0;
+        for region in self.board.empty_regions() {
+            let touches_atari = region.points.iter().any(|point| atari_liberties.contains(point));
+            if (region.borders_black && region.borders_white) || touches_atari {
+                disputed += region.points.len() as u32;
             }
         }
+        disputed
     }
 
-    pub fn handle_board_click(&mut self, board_x: usize, board_y: usize) -> String {
-        console_log!("Board click at ({}, {})", board_x, board_y);
-
-        if board_x >= self.board_size || board_y >= self.board_size {
-            return "Invalid move: Outside board bounds".to_string();
+    // Place a fixed handicap of 2-9 black stones on the standard star points, with White to
+    // move. Handicap stones are part of the setup position, so undo can never remove them.
+    // Only allowed before any moves have been played; 0 removes the handicap.
+    pub fn set_handicap(&mut self, stones: usize) -> bool {
+        if !self.move_sequence.is_empty() || stones == 1 || stones > 9 {
+            return false;
         }
 
-        if self.board[board_y][board_x] != StoneState::Empty {
-            return "Invalid move: Position already occupied".to_string();
+        self.setup_board = [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        for (x, y) in self.handicap_points(stones) {
+            self.setup_board[y][x] = StoneState::Black;
         }
+        self.setup_player = if stones == 0 { StoneState::Black } else { StoneState::White };
+        self.free_handicap = 0;
+        self.reconstruct_state_to_index(0);
 
-        let placed_stone = self.current_player;
-        let opponent = match placed_stone {
-            StoneState::Black => StoneState::White,
-            StoneState::White => StoneState::Black,
-            StoneState::Empty => StoneState::Empty,
-        };
+        console_log!("Placed {} handicap stones", stones);
+        true
+    }
 
-        // Check if this move would be suicidal
-        if self.is_suicidal_move(board_x, board_y, placed_stone) {
-            return "Invalid move: Cannot place stone that would be immediately captured (suicide rule)".to_string();
+    // Free handicap of 2-9 stones that Black may put anywhere. With `auto` they are placed
+    // now as setup stones with White to move, chosen by the seeded generator (see
+    // set_random_seed): the corner star points in random order first, then the other star
+    // points, then open points on the third and fourth lines. Otherwise Black plays the
+    // first `count` moves in a row, which become setup stones with White to move once the
+    // last one is down. Only before any moves are played.
+    pub fn place_free_handicap(&mut self, count: usize, auto: bool) -> bool {
+        if !self.move_sequence.is_empty() || !(2..=9).contains(&count) {
+            return false;
         }
 
-        // Remove any future moves if we're not at the end (truncate for new branch)
-        if self.move_index < self.move_sequence.len() {
-            self.move_sequence.truncate(self.move_index);
+        self.setup_board = [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+        if auto {
+            let points = self.free_handicap_points(count);
+            for &(x, y) in &points {
+                self.setup_board[y][x] = StoneState::Black;
+            }
+            self.setup_player = StoneState::White;
+            self.free_handicap = 0;
+            console_log!("Placed {} free handicap stones", points.len());
+        } else {
+            self.setup_player = StoneState::Black;
+            self.free_handicap = count;
+            console_log!("Black places {} free handicap stones", count);
         }
+        self.reconstruct_state_to_index(0);
+        true
+    }
 
-        // Add move to sequence
-        self.move_sequence.push(Move {
-            x: Some(board_x),
-            y: Some(board_y),
-            player: placed_stone,
-        });
-        self.move_index += 1;
+    // Reseed the generator behind random choices such as automatic free handicap placement;
+    // the same seed gives the same choices
+    pub fn set_random_seed(&mut self, seed: u32) {
+        self.rng = Rng::new(seed);
+    }
 
-        // Place the stone
-        self.board[board_y][board_x] = placed_stone;
+    // Stones still to place in a manual free handicap (0 once White is to move)
+    pub fn get_free_handicap_remaining(&self) -> usize {
+        self.free_handicap.saturating_sub(self.move_index)
+    }
 
-        // Assign move number to this position
-        self.move_numbers[board_y][board_x] = self.move_index as u32;
+    fn free_handicap_points(&mut self, count: usize) -> Vec<(usize, usize)> {
+        let mut corners = self.handicap_points(4);
+        let mut stars: Vec<(usize, usize)> = self.star_points().into_iter().filter(|point| !corners.contains(point)).collect();
+        let size = self.board.size;
+        let line = |x: usize, y: usize| x.min(size - 1 - x).min(y).min(size - 1 - y) + 1;
+        let mut lines: Vec<(usize, usize)> =
+            (0..size).flat_map(|y| (0..size).map(move |x| (x, y))).filter(|&(x, y)| (3..=4).contains(&line(x, y))).collect();
+        self.rng.shuffle(&mut corners);
+        self.rng.shuffle(&mut stars);
+        self.rng.shuffle(&mut lines);
+
+        // Keep the stones apart: no two on neighboring points
+        let mut points: Vec<(usize, usize)> = Vec::new();
+        for (x, y) in corners.into_iter().chain(stars).chain(lines) {
+            if points.len() == count {
+                break;
+            }
+            if points.iter().all(|&(px, py)| px.abs_diff(x) + py.abs_diff(y) > 1) {
+                points.push((x, y));
+            }
+        }
+        points
+    }
 
-        // Update last move position
-        self.last_move = Some((board_x, board_y));
+    // Check whether the game starts from a non-empty setup position or with White to move
+    pub fn has_setup(&self) -> bool {
+        self.setup_player != StoneState::Black
+            || (0..self.board.size).any(|y| self.setup_board[y][..self.board.size].iter().any(|&s| s != StoneState::Empty))
+    }
 
-        let mut total_captured = 0;
-        // Check all four adjacent positions for opponent groups to capture
-        let adjacent_positions = [
-            (board_x.wrapping_sub(1), board_y), // Left
-            (board_x + 1, board_y),             // Right
-            (board_x, board_y.wrapping_sub(1)), // Up
-            (board_x, board_y + 1),             // Down
-        ];
+    // Standard handicap stone placement order for the current board size
+    fn handicap_points(&self, stones: usize) -> Vec<(usize, usize)> {
+        let edge = if self.board.size >= 13 { 3 } else { 2 };
+        let (lo, mid, hi) = (edge, self.board.size / 2, self.board.size - 1 - edge);
 
-        for (adj_x, adj_y) in adjacent_positions {
-            if adj_x < self.board_size && adj_y < self.board_size {
-                if self.board[adj_y][adj_x] == opponent {
-                    let captured = self.capture_group_if_no_liberties(adj_x, adj_y, opponent);
-                    total_captured += captured;
-                }
-            }
-        }
+        let corners = [(hi, lo), (lo, hi), (hi, hi), (lo, lo)];
+        let sides = [(lo, mid), (hi, mid)];
+        let top_bottom = [(mid, lo), (mid, hi)];
+        let center = (mid, mid);
 
-        // Update capture count
-        match placed_stone {
-            StoneState::Black => self.black_captures += total_captured,
-            StoneState::White => self.white_captures += total_captured,
-            StoneState::Empty => {},
+        let mut points: Vec<(usize, usize)> = corners.iter().take(stones.min(4)).copied().collect();
+        if stones >= 6 {
+            points.extend_from_slice(&sides);
         }
-
-        if total_captured > 0 {
-            console_log!("Captured {} stones", total_captured);
+        if stones >= 8 {
+            points.extend_from_slice(&top_bottom);
         }
+        if stones >= 5 && stones % 2 == 1 {
+            points.push(center);
+        }
+        points
+    }
 
-        // Switch players
-        self.current_player = match self.current_player {
-            StoneState::Black => StoneState::White,
-            StoneState::White => StoneState::Black,
-            StoneState::Empty => StoneState::Black,
-        };
-
-        console_log!("Placed stone at ({}, {}), move index: {}", board_x, board_y, self.move_index);
-        "Move successful".to_string()
+    // List every connected empty region as JSON:
+    // [{"size","borders_black","borders_white","points":[[x, y], ...]}, ...]
+    // Regions are ordered by their first point in row-major order, as are the points within each.
+    pub fn get_empty_regions(&self) -> String {
+        let regions: Vec<String> = self
+            .board
+            .empty_regions()
+            .iter()
+            .map(|region| {
+                let points: Vec<String> = region.points.iter().map(|&(x, y)| format!("[{},{}]", x, y)).collect();
+                format!(
+                    "{{\"size\":{},\"borders_black\":{},\"borders_white\":{},\"points\":[{}]}}",
+                    region.points.len(),
+                    region.borders_black,
+                    region.borders_white,
+                    points.join(",")
+                )
+            })
+            .collect();
+        format!("[{}]", regions.join(","))
     }
 
-    pub fn undo(&mut self) -> bool {
-        if self.can_undo() {
-            self.move_index -= 1;
-            self.reconstruct_state_to_index(self.move_index);
-            console_log!("Undo: moved to move index {}", self.move_index);
-            true
-        } else {
-            false
-        }
+    // Every group on the board as a JSON array, in row-major order of each group's first
+    // stone (which is also its representative point). Built in a single flood-fill pass,
+    // so it is cheap enough to refresh after every move.
+    pub fn get_all_groups(&self) -> String {
+        let groups: Vec<String> = self
+            .board
+            .group_summaries()
+            .iter()
+            .map(|group| {
+                format!(
+                    "{{\"color\":{},\"stones\":{},\"liberties\":{},\"in_atari\":{},\"x\":{},\"y\":{}}}",
+                    group.color as u8,
+                    group.stones,
+                    group.liberties,
+                    group.liberties == 1,
+                    group.x,
+                    group.y
+                )
+            })
+            .collect();
+        format!("[{}]", groups.join(","))
     }
 
-    pub fn redo(&mut self) -> bool {
-        if self.can_redo() {
-            self.move_index += 1;
-            self.reconstruct_state_to_index(self.move_index);
-            console_log!("Redo: moved to move index {}", self.move_index);
-            true
-        } else {
-            false
+    // Stone, empty point, group and liberty counts as JSON: {"black_stones", "white_stones",
+    // "empty", "black_groups", "white_groups", "black_liberties", "white_liberties"}.
+    // Liberties are summed per group, so a point next to two groups counts for both.
+    pub fn get_board_stats(&self) -> String {
+        let mut stones = [0; 3];
+        let mut groups = [0; 3];
+        let mut liberties = [0; 3];
+        for group in self.board.group_summaries() {
+            stones[group.color as usize] += group.stones;
+            groups[group.color as usize] += 1;
+            liberties[group.color as usize] += group.liberties;
         }
-    }
+        let empty = self.board.size * self.board.size - stones[1] - stones[2];
 
-    pub fn can_undo(&self) -> bool {
-        self.move_index > 0
+        format!(
+            "{{\"black_stones\":{},\"white_stones\":{},\"empty\":{},\"black_groups\":{},\"white_groups\":{},\"black_liberties\":{},\"white_liberties\":{}}}",
+            stones[1], stones[2], empty, groups[1], groups[2], liberties[1], liberties[2]
+        )
     }
 
-    pub fn can_redo(&self) -> bool {
-        self.move_index < self.move_sequence.len()
+    pub fn set_komi(&mut self, komi: f32) {
+        self.komi = komi;
     }
 
-    pub fn resize(&mut self, width: u32, height: u32) {
-        self.canvas_width = width;
-        self.canvas_height = height;
+    pub fn get_komi(&self) -> f32 {
+        self.komi
     }
 
-    pub fn get_black_captures(&self) -> u32 {
-        self.black_captures
+    // Start a match of up to `games` games (1-99) with the current komi for even games and
+    // begin its first game on a cleared board, player one taking Black
+    pub fn start_match(&mut self, games: u32, rule: HandicapRule) -> bool {
+        if games == 0 || games > 99 {
+            return false;
+        }
+        self.game_match = Some(MatchState {
+            games,
+            rule,
+            base_komi: self.komi,
+            current_game: 1,
+            results: Vec::new(),
+        });
+        self.start_match_game();
+        true
     }
 
-    pub fn get_white_captures(&self) -> u32 {
-        self.white_captures
+    pub fn end_match(&mut self) {
+        self.game_match = None;
     }
 
-    // Get the last move position (returns None if no move has been made)
-    pub fn get_last_move(&self) -> Option<Box<[u32]>> {
-        match self.last_move {
-            Some((x, y)) => Some(vec![x as u32, y as u32].into_boxed_slice()),
-            None => None,
+    // Record the winner (1 = Black, 2 = White) of the current match game. Only once per
+    // game, and not after the match is decided.
+    pub fn record_result(&mut self, winner: u8) -> bool {
+        let Some(game_match) = self.game_match.as_mut() else { return false };
+        if game_match.results.len() as u32 >= game_match.current_game || game_match.is_finished() {
+            return false;
         }
+        let black_player = game_match.black_player();
+        let winner_player = match winner {
+            1 => black_player,
+            2 => 3 - black_player,
+            _ => return false,
+        };
+        game_match.results.push(winner_player);
+        true
     }
 
-    // Handle pass move - player passes their turn
-    pub fn handle_pass(&mut self) -> String {
-        console_log!("Player {} passes", match self.current_player {
-            StoneState::Black => "Black",
-            StoneState::White => "White",
-            StoneState::Empty => "Empty",
-        });
+    // Running match tally as JSON: games in the match, results so far (1 = player one,
+    // 2 = player two), wins per player, the current game and who has Black in it, its
+    // handicap stones and komi, and whether the match is decided. None without a match.
+    pub fn get_match_score(&self) -> Option<String> {
+        let game_match = self.game_match.as_ref()?;
+        let (handicap, komi) = game_match.game_setup();
+        let results: Vec<String> = game_match.results.iter().map(|winner| winner.to_string()).collect();
+        Some(format!(
+            "{{\"games\":{},\"results\":[{}],\"player_one\":{},\"player_two\":{},\"current_game\":{},\"black\":{},\"handicap\":{},\"komi\":{},\"finished\":{}}}",
+            game_match.games,
+            results.join(","),
+            game_match.wins(1),
+            game_match.wins(2),
+            game_match.current_game,
+            game_match.black_player(),
+            handicap,
+            komi,
+            game_match.is_finished()
+        ))
+    }
 
-        // Remove any future moves if we're not at the end (truncate for new branch)
-        if self.move_index < self.move_sequence.len() {
-            self.move_sequence.truncate(self.move_index);
+    // Move on to the next match game once the current one has a result: clears the board
+    // and applies the handicap and komi the match rule gives it
+    pub fn next_game(&mut self) -> bool {
+        let Some(game_match) = self.game_match.as_mut() else { return false };
+        if (game_match.results.len() as u32) < game_match.current_game || game_match.is_finished() {
+            return false;
         }
+        game_match.current_game += 1;
+        self.start_match_game();
+        true
+    }
 
-        // Add pass move to sequence
-        self.move_sequence.push(Move {
-            x: None,
-            y: None,
-            player: self.current_player,
-        });
-        self.move_index += 1;
-
-        // Switch players
-        self.current_player = match self.current_player {
-            StoneState::Black => StoneState::White,
-            StoneState::White => StoneState::Black,
-            StoneState::Empty => StoneState::Black,
-        };
-
-        // Clear last move since this was a pass
-        self.last_move = None;
-
-        "Pass successful".to_string()
+    // Clear the board for the current match game and apply its handicap and komi
+    fn start_match_game(&mut self) {
+        let Some((handicap, komi)) = self.game_match.as_ref().map(MatchState::game_setup) else { return };
+        self.stop_autoplay();
+        self.review = None;
+        self.score_accepted_at = None;
+        self.move_sequence.clear();
+        self.move_index = 0;
+        self.discarded_branches.clear();
+        self.komi = komi;
+        self.set_handicap(handicap);
+        self.notify_turn_change();
     }
 
-    // Serialize current game state to a compact string format
-    pub fn serialize_state(&self) -> String {
-        let mut state_bytes = Vec::new();
+    // Area-scoring gauge for a live score bar: Black's stones plus empty regions bordered
+    // only by Black, minus the same for White, minus komi. Every stone counts as alive, so
+    // the number is only meaningful once the position is settled; it's cheap enough to
+    // call after every move.
+    pub fn current_area_difference(&self) -> f32 {
+        self.board.score(self.komi)
+    }
 
-        // Pack board size (3 bits: 0=9, 1=13, 2=19) and current player (2 bits) into 1 byte
-        let board_size_code = match self.board_size {
-            9 => 0u8,
-            13 => 1u8,
-            19 => 2u8,
-            _ => 2u8, // Default to 19
-        };
-        let player_code = match self.current_player {
-            StoneState::Empty => 0u8,
-            StoneState::Black => 1u8,
-            StoneState::White => 2u8,
-        };
-        let header_byte = (board_size_code << 2) | player_code;
-        state_bytes.push(header_byte);
+    // Influence map for a "who controls what" overlay, by Bouzy's dilation and erosion
+    // (defaults 5 dilations and 10 erosions, which keep moyo; 5 and 21 narrow it down to
+    // territory). Returns a row-major board-sized array: positive values lean Black and
+    // negative White, with stones starting at +/-128 and strength fading with distance;
+    // 0 is neutral. Stones marked dead are ignored. Read-only.
+    pub fn get_influence_map(&self, dilations: Option<u32>, erosions: Option<u32>) -> Box<[f32]> {
+        const STONE_INFLUENCE: i32 = 128;
+        let size = self.board.size;
+
+        let mut influence = vec![0i32; size * size];
+        for y in 0..size {
+            for x in 0..size {
+                if self.dead_stones.contains(&(x, y)) {
+                    continue;
+                }
+                influence[y * size + x] = match self.board[y][x] {
+                    StoneState::Black => STONE_INFLUENCE,
+                    StoneState::White => -STONE_INFLUENCE,
+                    StoneState::Empty => 0,
+                };
+            }
+        }
 
-        // Variable-length encoding for capture counts (saves space for small numbers)
-        encode_varint(&mut state_bytes, self.black_captures);
-        encode_varint(&mut state_bytes, self.white_captures);
+        let neighbors = |i: usize| self.board.neighbors(i % size, i / size).iter().map(|&(nx, ny)| ny * size + nx);
 
-        // Encode move sequence up to current move_index
-        encode_varint(&mut state_bytes, self.move_index as u32);
-        for mv in self.move_sequence.iter().take(self.move_index) {
-            match (mv.x, mv.y) {
-                (Some(x), Some(y)) => {
-                    // Stone placement: encode position (9 bits for 19x19) + player (2 bits)
-                    let position = (y * self.board_size + x) as u16;
-                    let player_bits = match mv.player {
-                        StoneState::Black => 1u16,
-                        StoneState::White => 2u16,
-                        StoneState::Empty => 0u16,
-                    };
-                    let encoded = (position << 2) | player_bits;
-                    // Store as 2 bytes (little endian)
-                    state_bytes.push(encoded as u8);
-                    state_bytes.push((encoded >> 8) as u8);
-                }
-                (None, None) => {
-                    // Pass move: use special encoding 0xFFFF
-                    state_bytes.push(0xFF);
-                    state_bytes.push(0xFF);
+        // Dilation: a point not touching the other color gains one per neighbor of its own color
+        for _ in 0..dilations.unwrap_or(5) {
+            let previous = influence.clone();
+            for (i, value) in influence.iter_mut().enumerate() {
+                let v = previous[i];
+                if v >= 0 && neighbors(i).all(|n| previous[n] >= 0) {
+                    *value += neighbors(i).filter(|&n| previous[n] > 0).count() as i32;
                 }
-                (None, Some(_)) | (Some(_), None) => {
-                    // Invalid move data - this should never happen in a properly constructed move sequence
-                    console_log!("Warning: Invalid move data encountered during serialization");
+                if v <= 0 && neighbors(i).all(|n| previous[n] <= 0) {
+                    *value -= neighbors(i).filter(|&n| previous[n] < 0).count() as i32;
                 }
             }
         }
 
-        // Encode as base64
-        base64_encode(&state_bytes)
-    }
-
-    // Restore game state from a serialized string
-    pub fn deserialize_state(&mut self, state_str: &str) -> bool {
-        if let Some(state_bytes) = base64_decode(state_str) {
-            if state_bytes.is_empty() {
-                return false;
+        // Erosion: a point loses one per neighbor not of its own color, without changing sign
+        for _ in 0..erosions.unwrap_or(10) {
+            let previous = influence.clone();
+            for (i, value) in influence.iter_mut().enumerate() {
+                let v = previous[i];
+                if v > 0 {
+                    *value = (v - neighbors(i).filter(|&n| previous[n] <= 0).count() as i32).max(0);
+                } else if v < 0 {
+                    *value = (v + neighbors(i).filter(|&n| previous[n] >= 0).count() as i32).min(0);
+                }
             }
+        }
 
-            let mut idx = 0;
-
-            // Decode header byte
-            let header_byte = state_bytes[idx];
-            idx += 1;
-
-            let board_size_code = (header_byte >> 2) & 0b111;
-            let board_size = match board_size_code {
-                0 => 9,
-                1 => 13,
-                2 => 19,
-                _ => return false,
-            };
-
-            let player_code = header_byte & 0b11;
-            let _current_player = match player_code {
-                0 => StoneState::Empty,
-                1 => StoneState::Black,
-                2 => StoneState::White,
-                _ => return false,
-            };
+        influence.into_iter().map(|v| v as f32).collect()
+    }
 
-            // Decode variable-length capture counts (for validation)
-            if let Some((_black_captures, new_idx)) = decode_varint(&state_bytes, idx) {
-                idx = new_idx;
-                if let Some((_white_captures, new_idx)) = decode_varint(&state_bytes, idx) {
-                    idx = new_idx;
+    // Outlines of territory for rendering: every empty region bordered by one color only, as
+    // a JSON array of {"owner", "size", "segments": [[x1, y1, x2, y2], ...]}. Segments trace
+    // the region's outline halfway between intersections, in board coordinates, chained so
+    // each starts where the previous one ended (a region with holes has several loops in a
+    // row). Outlines of regions touching the board edge run along the edge line.
+    pub fn get_territory_regions(&self) -> String {
+        let max = (self.board.size - 1) as f32;
+        let to_board = |corner: usize| (corner as f32 - 0.5).clamp(0.0, max);
+
+        let regions: Vec<String> = self
+            .board
+            .empty_regions()
+            .iter()
+            .filter(|region| region.borders_black != region.borders_white)
+            .map(|region| {
+                let owner = if region.borders_black { StoneState::Black } else { StoneState::White };
+                let segments: Vec<String> = region_outline(&region.points)
+                    .iter()
+                    .map(|&((x1, y1), (x2, y2))| format!("[{},{},{},{}]", to_board(x1), to_board(y1), to_board(x2), to_board(y2)))
+                    .collect();
+                format!(
+                    "{{\"owner\":{},\"size\":{},\"segments\":[{}]}}",
+                    owner as u8,
+                    region.points.len(),
+                    segments.join(",")
+                )
+            })
+            .collect();
+        format!("[{}]", regions.join(","))
+    }
 
-                    // Decode move count
-                    if let Some((move_count, new_idx)) = decode_varint(&state_bytes, idx) {
-                        idx = new_idx;
+    // Capture Go (Atari Go) variant: the first player to capture `first_to` stones wins.
+    // 0 turns the variant off. Two passes in a row end a capture go game with no winner.
+    pub fn set_capture_go(&mut self, first_to: u32) {
+        self.capture_go_target = first_to;
+    }
 
-                        // Decode move sequence
-                        let mut move_sequence = Vec::new();
-                        for _ in 0..move_count {
-                            if idx + 1 >= state_bytes.len() {
-                                return false;
-                            }
+    pub fn get_capture_go(&self) -> u32 {
+        self.capture_go_target
+    }
 
-                            let encoded = state_bytes[idx] as u16 | ((state_bytes[idx + 1] as u16) << 8);
-                            idx += 2;
-
-                            if encoded == 0xFFFF {
-                                // Pass move
-                                // Player alternates: Black starts, so odd moves are Black, even are White
-                                let player = if move_sequence.len() % 2 == 0 {
-                                    StoneState::Black
-                                } else {
-                                    StoneState::White
-                                };
-                                move_sequence.push(Move {
-                                    x: None,
-                                    y: None,
-                                    player,
-                                });
-                            } else {
-                                // Stone placement
-                                let position = (encoded >> 2) as usize;
-                                let player_bits = encoded & 0b11;
-                                let player = match player_bits {
-                                    1 => StoneState::Black,
-                                    2 => StoneState::White,
-                                    _ => return false,
-                                };
-
-                                let x = position % board_size;
-                                let y = position / board_size;
-
-                                if x >= board_size || y >= board_size {
-                                    return false;
-                                }
+    // Rules variant by name: "standard", or "capture_go" where the first capture wins
+    // (set_capture_go allows a higher target). Carried in serialized states. False for an
+    // unknown name.
+    pub fn set_variant(&mut self, variant: &str) -> bool {
+        match variant {
+            "standard" => self.set_capture_go(0),
+            "capture_go" => self.set_capture_go(1),
+            _ => return false,
+        }
+        true
+    }
 
-                                move_sequence.push(Move {
-                                    x: Some(x),
-                                    y: Some(y),
-                                    player,
-                                });
-                            }
-                        }
+    pub fn get_variant(&self) -> String {
+        if self.capture_go_target == 0 { "standard" } else { "capture_go" }.to_string()
+    }
 
-                        // Update game state
-                        self.board_size = board_size;
-                        self.move_sequence = move_sequence;
-                        self.move_index = move_count as usize;
+    // Result of a game decided without counting, in SGF RE form: "B+Capture" or "W+Capture"
+    // for capture go, "Void" for a repetition cycle or capture go ended by passes. None while
+    // the game goes on or once a counted score is accepted.
+    pub fn get_result(&self) -> Option<String> {
+        match (self.game_over_reason()?, self.get_winner()) {
+            (GameOverReason::CaptureGo, 1) => Some("B+Capture".to_string()),
+            (GameOverReason::CaptureGo, _) => Some("W+Capture".to_string()),
+            (GameOverReason::Void, _) => Some("Void".to_string()),
+            (GameOverReason::ScoreAccepted, _) => None,
+        }
+    }
 
-                        // Reconstruct the current game state
-                        self.reconstruct_state_to_index(self.move_index);
+    // Whether the game has been decided, by capture go, by accepting the score or by a
+    // repetition cycle. Derived from the current position, so undoing the deciding move
+    // reopens the game.
+    pub fn is_game_over(&self) -> bool {
+        self.game_over_reason().is_some()
+    }
 
-                        console_log!("Successfully deserialized game state with {} moves", move_count);
-                        return true;
-                    }
-                }
-            }
+    // Why the game ended: "Capture Go" (see get_winner), "Score accepted", or "Void/No result"
+    // for a repetition cycle (see set_repetition_limit) or a capture go game ended by two
+    // passes; None while it goes on
+    pub fn get_game_over_reason(&self) -> Option<String> {
+        self.game_over_reason().map(|reason| reason.message().to_string())
+    }
 
-            false
+    fn game_over_reason(&self) -> Option<GameOverReason> {
+        if self.get_winner() != 0 {
+            Some(GameOverReason::CaptureGo)
+        } else if self.score_accepted_at == Some(self.move_index) {
+            Some(GameOverReason::ScoreAccepted)
+        } else if self.repetition_void || (self.capture_go_target > 0 && self.in_scoring_phase()) {
+            Some(GameOverReason::Void)
         } else {
-            false
+            None
         }
     }
 
-    // Check if a group has any liberties (empty adjacent spaces)
-    fn has_liberties(&self, x: usize, y: usize, color: StoneState, visited: &mut [[bool; MAX_BOARD_SIZE]; MAX_BOARD_SIZE]) -> bool {
-        if visited[y][x] || self.board[y][x] != color {
-            return false;
-        }
-
-        visited[y][x] = true;
-
-        // Check all four adjacent positions
-        let adjacent_positions = [
-            (x.wrapping_sub(1), y), // Left
-            (x + 1, y),             // Right
-            (x, y.wrapping_sub(1)), // Up
-            (x, y + 1),             // Down
-        ];
-
-        for (adj_x, adj_y) in adjacent_positions {
-            if adj_x < self.board_size && adj_y < self.board_size {
-                if self.board[adj_y][adj_x] == StoneState::Empty {
-                    return true; // Found a liberty
-                } else if self.board[adj_y][adj_x] == color {
-                    // Check connected stones of the same color
-                    if self.has_liberties(adj_x, adj_y, color, visited) {
-                        return true;
-                    }
-                }
-            }
+    // Winner of a decided game: 1 = Black, 2 = White, 0 = undecided
+    pub fn get_winner(&self) -> u8 {
+        if self.capture_go_target == 0 {
+            return 0;
+        }
+        if self.black_captures >= self.capture_go_target {
+            1
+        } else if self.white_captures >= self.capture_go_target {
+            2
+        } else {
+            0
         }
-
-        false
     }
 
-    // Capture a group if it has no liberties, return number of captured stones
-    fn capture_group_if_no_liberties(&mut self, x: usize, y: usize, color: StoneState) -> u32 {
-        let mut visited = [[false; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
-
-        // Check if the group has liberties
-        if self.has_liberties(x, y, color, &mut visited) {
-            return 0; // Group has liberties, don't capture
+    // Edit mode: set a point to 0 (Empty), 1 (Black) or 2 (White) outside the move history.
+    // Afterwards an edited point has no move number and no longer carries the last-move
+    // marker, any pending ko is cleared, and with no moves played the edit becomes part of
+    // the setup position.
+    pub fn set_board_position(&mut self, x: usize, y: usize, state: u8) -> MoveResult {
+        if x >= self.board.size || y >= self.board.size {
+            return MoveResult::OutOfBounds;
         }
 
-        // Group has no liberties, capture all stones in the group
-        let mut captured = 0;
-        let mut to_capture = Vec::new();
-        self.find_group_stones(x, y, color, &mut to_capture);
+        let stone_state = match state {
+            0 => StoneState::Empty,
+            1 => StoneState::Black,
+            2 => StoneState::White,
+            _ => return MoveResult::InvalidState,
+        };
 
-        for (cap_x, cap_y) in to_capture {
-            self.board[cap_y][cap_x] = StoneState::Empty;
-            self.move_numbers[cap_y][cap_x] = 0; // Clear move number when captured
-            captured += 1;
+        if self.review.is_some() {
+            return MoveResult::ReviewActive;
         }
 
-        console_log!("Captured group of {} stones at ({}, {})", captured, x, y);
-        captured
-    }
+        console_log!("Edited ({}, {}) to {:?}", x, y, stone_state);
+        if self.board[y][x] != stone_state {
+            self.board[y][x] = stone_state;
 
-    // Find all stones in a connected group of the same color
-    fn find_group_stones(&self, x: usize, y: usize, color: StoneState, group: &mut Vec<(usize, usize)>) {
-        if x >= self.board_size || y >= self.board_size || self.board[y][x] != color {
-            return;
+            // The stone here (if any) was not placed by a numbered move
+            self.move_numbers[y][x] = 0;
+            if self.last_action == LastAction::Stone(x, y) {
+                self.last_action = LastAction::None;
+            }
         }
 
-        // Check if already in group
-        if group.contains(&(x, y)) {
-            return;
+        // Edits invalidate any pending ko, dead-stone marks and an accepted score
+        self.board.ko = None;
+        self.dead_stones.clear();
+        self.score_accepted_at = None;
+
+        // Before any moves are played, edits define the setup position moves replay from
+        if self.move_sequence.is_empty() {
+            self.setup_board[y][x] = stone_state;
+        } else {
+            self.position_edited = true;
         }
 
-        group.push((x, y));
+        // Keep the hash of the current position in sync with the edit
+        self.hash_history[self.move_index] = (self.board_hash(), self.current_player);
+        self.update_repetition();
+        self.run_debug_checks("edit");
 
-        // Recursively find connected stones
-        let adjacent_positions = [
-            (x.wrapping_sub(1), y), // Left
-            (x + 1, y),             // Right
-            (x, y.wrapping_sub(1)), // Up
-            (x, y + 1),             // Down
-        ];
+        MoveResult::Success
+    }
+}
 
-        for (adj_x, adj_y) in adjacent_positions {
-            if adj_x < self.board_size && adj_y < self.board_size {
-                self.find_group_stones(adj_x, adj_y, color, group);
-            }
-        }
+// Status of a single eye region by shape (see eye_space_status): spaces of one or two
+// points and the square four are dead, the nakade shapes (three in a row or bent, pyramid
+// four, bulky five, crossed five, rabbity six) are killable, everything else is alive
+fn eye_shape_status(points: &[(usize, usize)]) -> i8 {
+    const SQUARE_FOUR: &[(i32, i32)] = &[(0, 0), (1, 0), (0, 1), (1, 1)];
+    const NAKADE_SHAPES: &[&[(i32, i32)]] = &[
+        &[(0, 0), (1, 0), (2, 0)],                         // Straight three
+        &[(0, 0), (1, 0), (1, 1)],                         // Bent three
+        &[(0, 0), (1, 0), (2, 0), (1, 1)],                 // Pyramid four
+        &[(0, 0), (1, 0), (0, 1), (1, 1), (0, 2)],         // Bulky five
+        &[(1, 0), (0, 1), (1, 1), (2, 1), (1, 2)],         // Crossed five
+        &[(1, 0), (0, 1), (1, 1), (2, 1), (1, 2), (2, 2)], // Rabbity six
+    ];
+
+    if points.len() <= 2 {
+        return -1;
+    }
+    let shape = canonical_shape(&points.iter().map(|&(x, y)| (x as i32, y as i32)).collect::<Vec<_>>());
+    if shape == canonical_shape(SQUARE_FOUR) {
+        -1
+    } else if NAKADE_SHAPES.iter().any(|nakade| shape == canonical_shape(nakade)) {
+        0
+    } else {
+        1
     }
+}
 
-    // Check if placing a stone would be suicidal (violate suicide rule)
-    fn is_suicidal_move(&self, x: usize, y: usize, color: StoneState) -> bool {
-        // Temporarily place the stone to test
-        let mut test_board = self.board;
-        test_board[y][x] = color;
+// Normalize a set of points for shape comparison: the smallest sorted, origin-translated
+// form over all eight rotations and reflections
+fn canonical_shape(points: &[(i32, i32)]) -> Vec<(i32, i32)> {
+    type Transform = fn((i32, i32)) -> (i32, i32);
+    let transforms: [Transform; 8] = [
+        |(x, y)| (x, y),
+        |(x, y)| (-x, y),
+        |(x, y)| (x, -y),
+        |(x, y)| (-x, -y),
+        |(x, y)| (y, x),
+        |(x, y)| (-y, x),
+        |(x, y)| (y, -x),
+        |(x, y)| (-y, -x),
+    ];
+
+    transforms
+        .iter()
+        .map(|transform| {
+            let moved: Vec<(i32, i32)> = points.iter().map(|&p| transform(p)).collect();
+            let min_x = moved.iter().map(|p| p.0).min().unwrap_or(0);
+            let min_y = moved.iter().map(|p| p.1).min().unwrap_or(0);
+            let mut shape: Vec<(i32, i32)> = moved.iter().map(|&(x, y)| (x - min_x, y - min_y)).collect();
+            shape.sort();
+            shape
+        })
+        .min()
+        .unwrap_or_default()
+}
 
-        let opponent = match color {
-            StoneState::Black => StoneState::White,
-            StoneState::White => StoneState::Black,
-            StoneState::Empty => return false,
-        };
+fn is_supported_board_size(board_size: usize) -> bool {
+    matches!(board_size, 7 | 9 | 11 | 13 | 15 | 17 | 19)
+}
 
-        // First check if this move would capture any opponent groups
-        // If it captures opponents, it's not suicidal even if it has no liberties
-        let adjacent_positions = [
-            (x.wrapping_sub(1), y), // Left
-            (x + 1, y),             // Right
-            (x, y.wrapping_sub(1)), // Up
-            (x, y + 1),             // Down
-        ];
+// Capitalized color name for a displayed player code (1 = Black, 2 = White)
+fn color_word(player: u8) -> &'static str {
+    if player == 2 { "White" } else { "Black" }
+}
 
-        for (adj_x, adj_y) in adjacent_positions {
-            if adj_x < self.board_size && adj_y < self.board_size {
-                if test_board[adj_y][adj_x] == opponent {
-                    // Check if this opponent group would be captured
-                    let mut visited = [[false; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
-                    if !self.has_liberties_on_board(&test_board, adj_x, adj_y, opponent, &mut visited) {
-                        // This move would capture opponent stones, so it's not suicidal
-                        return false;
+// The other player's color (Empty maps to Black, who moves first by default)
+fn opponent_of(color: StoneState) -> StoneState {
+    match color {
+        StoneState::Black => StoneState::White,
+        StoneState::White => StoneState::Black,
+        StoneState::Empty => StoneState::Black,
+    }
+}
+
+// One SGF node: its properties as (identifier, values) in document order
+type SgfNode = Vec<(String, Vec<String>)>;
+
+// Parse the main line of an SGF collection (the first game, following the first variation
+// at every branch) into its nodes. None if the text isn't well-formed SGF.
+fn parse_sgf_main_line(sgf: &str) -> Option<Vec<SgfNode>> {
+    let mut chars = sgf.chars().peekable();
+    let mut nodes: Vec<SgfNode> = Vec::new();
+    let mut started = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '(' => started = true,
+            // The main line ends where its first variation closes
+            ')' => break,
+            ';' if started => nodes.push(Vec::new()),
+            c if c.is_ascii_uppercase() => {
+                let node = nodes.last_mut()?;
+                let mut ident = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !next.is_ascii_uppercase() {
+                        break;
+                    }
+                    ident.push(next);
+                    chars.next();
+                }
+
+                let mut values = Vec::new();
+                loop {
+                    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+                        chars.next();
+                    }
+                    if chars.peek() != Some(&'[') {
+                        break;
+                    }
+                    chars.next();
+                    let mut value = String::new();
+                    loop {
+                        match chars.next()? {
+                            '\\' => value.push(chars.next()?),
+                            ']' => break,
+                            c => value.push(c),
+                        }
                     }
+                    values.push(value);
+                }
+                if values.is_empty() {
+                    return None;
                 }
+                node.push((ident, values));
             }
+            c if c.is_whitespace() => {}
+            _ => return None,
         }
-
-        // Now check if the placed stone (and its group) would have any liberties
-        let mut visited = [[false; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
-        !self.has_liberties_on_board(&test_board, x, y, color, &mut visited)
     }
 
-    // Check liberties on a specific board state (for testing moves)
-    fn has_liberties_on_board(&self, board: &[[StoneState; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], x: usize, y: usize, color: StoneState, visited: &mut [[bool; MAX_BOARD_SIZE]; MAX_BOARD_SIZE]) -> bool {
-        if visited[y][x] || board[y][x] != color {
-            return false;
-        }
+    started.then_some(nodes)
+}
 
-        visited[y][x] = true;
+// The outline of a set of points as chained edges between cell corners, where corner
+// (cx, cy) is the corner up and to the left of intersection (cx, cy). Edges run clockwise
+// around the region; each loop is emitted in order before the next one starts.
+fn region_outline(points: &[(usize, usize)]) -> Vec<((usize, usize), (usize, usize))> {
+    let mut inside = [[false; MAX_BOARD_SIZE + 1]; MAX_BOARD_SIZE + 1];
+    for &(x, y) in points {
+        inside[y][x] = true;
+    }
+    let is_inside = |x: usize, y: usize, dx: isize, dy: isize| {
+        let (nx, ny) = (x as isize + dx, y as isize + dy);
+        nx >= 0 && ny >= 0 && inside[ny as usize][nx as usize]
+    };
 
-        // Check all four adjacent positions
-        let adjacent_positions = [
-            (x.wrapping_sub(1), y), // Left
-            (x + 1, y),             // Right
-            (x, y.wrapping_sub(1)), // Up
-            (x, y + 1),             // Down
-        ];
+    // Each cell side facing outside the region, oriented clockwise
+    let mut edges = Vec::new();
+    for &(x, y) in points {
+        if !is_inside(x, y, 0, -1) {
+            edges.push(((x, y), (x + 1, y)));
+        }
+        if !is_inside(x, y, 1, 0) {
+            edges.push(((x + 1, y), (x + 1, y + 1)));
+        }
+        if !is_inside(x, y, 0, 1) {
+            edges.push(((x + 1, y + 1), (x, y + 1)));
+        }
+        if !is_inside(x, y, -1, 0) {
+            edges.push(((x, y + 1), (x, y)));
+        }
+    }
 
-        for (adj_x, adj_y) in adjacent_positions {
-            if adj_x < self.board_size && adj_y < self.board_size {
-                if board[adj_y][adj_x] == StoneState::Empty {
-                    return true; // Found a liberty
-                } else if board[adj_y][adj_x] == color {
-                    // Check connected stones of the same color
-                    if self.has_liberties_on_board(board, adj_x, adj_y, color, visited) {
-                        return true;
-                    }
+    // Chain edges end to start into loops
+    let mut outline = Vec::with_capacity(edges.len());
+    while let Some(first) = edges.pop() {
+        outline.push(first);
+        let mut end = first.1;
+        while end != first.0 {
+            match edges.iter().position(|edge| edge.0 == end) {
+                Some(i) => {
+                    let edge = edges.swap_remove(i);
+                    end = edge.1;
+                    outline.push(edge);
                 }
+                None => break,
             }
         }
+    }
+    outline
+}
 
-        false
+// Built-in joseki dictionary for match_corner. Moves are corner-local SGF-style points
+// ("dd" = 4-4: distance from the side edge, then from the top or bottom edge), each
+// prefixed with the player: B is whoever played first in the corner, W the other side.
+const JOSEKI: &[(&str, &str)] = &[
+    ("4-4 point, 3-3 invasion", "Bdd Wcc Bdc Wcd Bce Wbe Bbf"),
+    ("4-4 point, knight's approach, knight's move response", "Bdd Wfc Bcf"),
+    ("4-4 point, knight's approach, one-space jump", "Bdd Wfc Bdf"),
+    ("4-4 point, knight's approach, one-space low pincer", "Bdd Wfc Bhc"),
+    ("4-4 point, knight's approach, two-space high pincer", "Bdd Wfc Bid"),
+    ("4-4 point, high approach", "Bdd Wfd"),
+    ("4-4 point, knight's move enclosure", "Bdd Bcf"),
+    ("3-3 point, shoulder hit", "Bcc Wdd"),
+    ("3-4 point, small knight enclosure", "Bcd Bec"),
+    ("3-4 point, one-space enclosure", "Bcd Bed"),
+    ("3-4 point, small knight approach", "Bcd Wec"),
+    ("3-4 point, one-space high approach", "Bcd Wed"),
+];
+
+// Parse a JOSEKI move list into (dx, dy, played by the corner's first player)
+fn joseki_moves(moves: &str) -> Vec<(usize, usize, bool)> {
+    moves
+        .split_whitespace()
+        .filter_map(|mv| match mv.as_bytes() {
+            [player, col, row] => Some(((col - b'a') as usize, (row - b'a') as usize, *player == b'B')),
+            _ => None,
+        })
+        .collect()
+}
+
+// Board size for the 3-bit size code stored in the serialized header byte. The original
+// sizes keep codes 0-2; the odd variant sizes were added after them.
+fn board_size_from_code(code: u8) -> Option<usize> {
+    match code {
+        0 => Some(9),
+        1 => Some(13),
+        2 => Some(19),
+        3 => Some(7),
+        4 => Some(11),
+        5 => Some(15),
+        6 => Some(17),
+        _ => None,
     }
+}
 
-    // Check if there are any stones on the board
-    pub fn has_stones_on_board(&self) -> bool {
-        for y in 0..self.board_size {
-            for x in 0..self.board_size {
-                if self.board[y][x] != StoneState::Empty {
-                    return true;
-                }
-            }
+// Extension record tags for the serialized state. Each record is a tag byte, a varint
+// payload length and the payload, so decoders can skip tags they don't understand.
+const EXT_PLAY_AREA: u8 = 1;
+const EXT_SETUP: u8 = 2;
+const EXT_CHECKSUM: u8 = 3;
+const EXT_MATCH: u8 = 4;
+const EXT_ANNOTATIONS: u8 = 5;
+const EXT_CAPTURE_GO: u8 = 6;
+const EXT_FREE_HANDICAP: u8 = 7;
+
+// Newest serialized format version (header bits 5-7); version 1 adds EXT_CHECKSUM and
+// version 2 pass runs
+const FORMAT_VERSION: u8 = 2;
+
+// Move words from PASS_RUN up to 0xFFFB encode a run of consecutive passes alternating in
+// color: PASS_RUN | (run length << 2) | color of the first pass
+const PASS_RUN: u16 = 0xF000;
+const MAX_PASS_RUN: usize = 1022;
+
+fn encode_extension(bytes: &mut Vec<u8>, tag: u8, payload: &[u8]) {
+    bytes.push(tag);
+    encode_varint(bytes, payload.len() as u32);
+    bytes.extend_from_slice(payload);
+}
+
+fn decode_extensions(bytes: &[u8], mut idx: usize) -> Option<Vec<(u8, Vec<u8>)>> {
+    let mut extensions = Vec::new();
+    while idx < bytes.len() {
+        let tag = bytes[idx];
+        let (len, new_idx) = decode_varint(bytes, idx + 1)?;
+        let end = new_idx.checked_add(len as usize)?;
+        if end > bytes.len() {
+            return None;
         }
-        false
+        extensions.push((tag, bytes[new_idx..end].to_vec()));
+        idx = end;
     }
+    Some(extensions)
+}
 
-    // Directly set a board position for edit mode
-    pub fn set_board_position(&mut self, x: usize, y: usize, state: u8) -> String {
-        if x >= self.board_size || y >= self.board_size {
-            return "Invalid position".to_string();
-        }
+// Decode a setup record: the player to move followed by 2-byte (position << 2 | color) stones
+fn decode_setup(payload: &[u8], board_size: usize) -> Option<([[StoneState; MAX_BOARD_SIZE]; MAX_BOARD_SIZE], StoneState)> {
+    let (&player_code, stones) = payload.split_first()?;
+    let player = match player_code {
+        1 => StoneState::Black,
+        2 => StoneState::White,
+        _ => return None,
+    };
+    if stones.len() % 2 != 0 {
+        return None;
+    }
 
-        let stone_state = match state {
-            0 => StoneState::Empty,
+    let mut board = [[StoneState::Empty; MAX_BOARD_SIZE]; MAX_BOARD_SIZE];
+    for pair in stones.chunks(2) {
+        let encoded = pair[0] as u16 | ((pair[1] as u16) << 8);
+        let position = (encoded >> 2) as usize;
+        if position >= board_size * board_size {
+            return None;
+        }
+        board[position / board_size][position % board_size] = match encoded & 0b11 {
             1 => StoneState::Black,
             2 => StoneState::White,
-            _ => return "Invalid state".to_string(),
+            _ => return None,
         };
+    }
+    Some((board, player))
+}
 
-        self.board[y][x] = stone_state;
-
-        // Clear move number when setting position in edit mode
-        if stone_state == StoneState::Empty {
-            self.move_numbers[y][x] = 0;
-        }
-
-        return "Position set successfully".to_string();
+// Invoke a JS listener, logging (rather than propagating) anything it throws so a
+// misbehaving callback can't interrupt the engine mid-update
+fn call_listener(name: &str, callback: &js_sys::Function, args: &js_sys::Array) {
+    if let Err(err) = callback.apply(&JsValue::NULL, args) {
+        console_log!("Error in {} callback: {:?}", name, err);
     }
 }
 
@@ -843,3 +5925,849 @@ fn base64_decode(data: &str) -> Option<Vec<u8>> {
 pub fn init() {
     console_log!("WASM module loaded successfully!");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SIZES: [usize; 7] = [7, 9, 11, 13, 15, 17, 19];
+
+    // A blank game with console output off (the console binding only exists under wasm)
+    fn game(size: usize) -> GoGame {
+        VERBOSE.with(|verbose| verbose.set(false));
+        GoGame::blank(size)
+    }
+
+    #[test]
+    fn fuzz_keeps_invariants_on_every_size() {
+        VERBOSE.with(|verbose| verbose.set(false));
+        for size in SIZES {
+            for seed in 1..=3 {
+                assert_eq!(GoGame::fuzz(seed, 1000, size), "OK", "seed {} on {}x{}", seed, size, size);
+            }
+        }
+    }
+
+    #[test]
+    fn random_legal_play_keeps_invariants() {
+        for size in SIZES {
+            for seed in 1..=2 {
+                let mut rng = Rng::new(seed * 7919 + size as u32);
+                let mut game = game(size);
+                for step in 0..400 {
+                    match rng.below(20) {
+                        0 => {
+                            game.undo_n(1 + rng.below(4));
+                        }
+                        1 => {
+                            game.redo_n(1 + rng.below(4));
+                        }
+                        2 => {
+                            let mut loaded = self::game(size);
+                            assert!(loaded.deserialize_state(&game.serialize_state()), "step {}", step);
+                            assert_eq!(loaded.board_hash(), game.board_hash(), "step {}", step);
+                            game = loaded;
+                        }
+                        _ => {
+                            let legal = game.get_legal_moves();
+                            if legal.is_empty() || game.is_game_over() {
+                                game.undo_n(2);
+                            } else {
+                                let i = rng.below(legal.len() / 2);
+                                let result = game.play_move(legal[2 * i] as usize, legal[2 * i + 1] as usize);
+                                assert_eq!(result, MoveResult::Success, "step {}", step);
+                            }
+                        }
+                    }
+                    assert_eq!(game.check_invariants(), "OK", "seed {} on {}x{} at step {}", seed, size, size, step);
+                }
+            }
+        }
+    }
+
+    // Play stones for alternating players, asserting each is legal
+    fn play(game: &mut GoGame, moves: &[(usize, usize)]) {
+        for &(x, y) in moves {
+            assert_eq!(game.play_move(x, y), MoveResult::Success, "move at ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn identical_games_serialize_to_identical_bytes() {
+        let moves = [(4, 2), (4, 6), (2, 4), (6, 4), (4, 4), (3, 4)];
+
+        let mut first = game(9);
+        first.set_handicap(2);
+        play(&mut first, &moves);
+        first.handle_pass();
+
+        // The same game reached through a discarded line, undo and redo
+        let mut second = game(9);
+        second.set_handicap(2);
+        play(&mut second, &[(0, 0), (8, 8), (1, 1)]);
+        second.undo_all();
+        play(&mut second, &moves[..4]);
+        play(&mut second, &moves[4..]);
+        second.undo_n(2);
+        second.redo_n(2);
+        second.handle_pass();
+
+        assert_eq!(first.serialize_bytes(), second.serialize_bytes());
+    }
+
+
+    #[test]
+    fn string_and_byte_serialization_agree() {
+        let mut original = game(9);
+        original.set_handicap(3);
+        // White moves first after handicap; Black captures the white stone at (1, 0)
+        play(&mut original, &[(1, 0), (0, 0), (4, 4), (2, 0), (5, 5), (1, 1)]);
+        original.handle_pass();
+        assert_eq!((original.black_captures, original.white_captures), (1, 0));
+
+        let bytes = original.serialize_bytes();
+        let state = original.serialize_state();
+        assert_eq!(state, base64_encode(&bytes));
+
+        let mut from_string = game(9);
+        assert!(from_string.deserialize_state(&state));
+        let mut from_bytes = game(9);
+        assert!(from_bytes.deserialize_bytes(&bytes));
+
+        let summary = |g: &GoGame| (g.board_hash(), g.black_captures, g.white_captures, g.current_player, g.move_index, g.serialize_bytes());
+        assert_eq!(summary(&from_string), summary(&from_bytes));
+        assert_eq!(summary(&from_string), summary(&original));
+    }
+
+
+    #[test]
+    fn trailing_pass_runs_round_trip() {
+        for run in [1, 2, 3, MAX_PASS_RUN, MAX_PASS_RUN + 5] {
+            let mut original = game(9);
+            play(&mut original, &[(4, 4)]);
+            for _ in 0..run {
+                original.append_pass();
+            }
+
+            let mut loaded = game(9);
+            assert!(loaded.deserialize_state(&original.serialize_state()), "run of {}", run);
+            assert_eq!(loaded.move_sequence.len(), run + 1, "run of {}", run);
+            assert_eq!(loaded.move_index, run + 1, "run of {}", run);
+            for (i, mv) in loaded.move_sequence[1..].iter().enumerate() {
+                let expected = if i % 2 == 0 { StoneState::White } else { StoneState::Black };
+                assert!(mv.x.is_none() && mv.y.is_none(), "run of {}: move {} isn't a pass", run, i + 1);
+                assert_eq!(mv.player, expected, "run of {}: pass {}", run, i + 1);
+                assert_eq!(mv.next_player, opponent_of(expected), "run of {}: pass {}", run, i + 1);
+            }
+            assert_eq!(loaded.current_player, original.current_player, "run of {}", run);
+            assert_eq!(loaded.serialize_bytes(), original.serialize_bytes(), "run of {}", run);
+        }
+    }
+
+    #[test]
+    fn passes_are_one_word_each_without_a_format_version() {
+        let mut original = game(9);
+        original.set_serialize_checksum(false);
+        play(&mut original, &[(4, 4)]);
+        for _ in 0..3 {
+            original.append_pass();
+        }
+
+        // Header, two capture counts and the move count, then one word per move
+        let bytes = original.serialize_bytes();
+        assert_eq!(bytes[0] >> 5, 0);
+        assert_eq!(bytes.len(), 4 + 2 * 4);
+        assert_eq!(&bytes[6..], &[0xFE, 0xFF, 0xFD, 0xFF, 0xFE, 0xFF]);
+
+        let mut loaded = game(9);
+        assert!(loaded.deserialize_state(&original.serialize_state()));
+        assert_eq!(loaded.move_sequence.len(), 4);
+        loaded.set_serialize_checksum(false);
+        assert_eq!(loaded.serialize_bytes(), bytes);
+
+        // A pass run word is only understood from version 2
+        let mut with_run = bytes[..6].to_vec();
+        with_run[3] = 3;
+        with_run.extend_from_slice(&(PASS_RUN | (3 << 2) | 2).to_le_bytes());
+        assert!(!loaded.deserialize_bytes(&with_run));
+    }
+
+    #[test]
+    fn click_edges_snap_or_reject_on_every_standard_size() {
+        for size in [9, 13, 19] {
+            let mut game = game(size);
+            game.set_click_tolerance(0.05);
+            let last = size - 1;
+            let resolve = |x: f32, y: f32| game.click_to_intersection(x, y);
+
+            // Edge lines and clicks just inside the tolerance snap to the edge
+            assert_eq!(resolve(-1.0, -1.0), Some((0, 0)), "{}x{}", size, size);
+            assert_eq!(resolve(1.0, 1.0), Some((last, last)), "{}x{}", size, size);
+            assert_eq!(resolve(-1.0, 1.0), Some((0, last)), "{}x{}", size, size);
+            assert_eq!(resolve(-1.049, 1.049), Some((0, last)), "{}x{}", size, size);
+            assert_eq!(resolve(1.049, -1.049), Some((last, 0)), "{}x{}", size, size);
+            assert_eq!(resolve(1.05, -1.05), Some((last, 0)), "{}x{}", size, size);
+
+            // Beyond the tolerance, on either axis, is off the board
+            for (x, y) in [(-1.051, 0.0), (1.051, 0.0), (0.0, -1.051), (0.0, 1.051), (-1.5, -1.5)] {
+                assert_eq!(resolve(x, y), None, "({}, {}) on {}x{}", x, y, size, size);
+            }
+            for (x, y) in [(f32::NAN, 0.0), (0.0, f32::NAN), (f32::INFINITY, 0.0), (0.0, f32::NEG_INFINITY)] {
+                assert_eq!(resolve(x, y), None, "({}, {}) on {}x{}", x, y, size, size);
+            }
+
+            // Halfway between two lines rounds to the nearer one on either side of the midpoint
+            for line in 0..last {
+                let midpoint = (2 * line + 1) as f32 / last as f32 - 1.0;
+                assert_eq!(resolve(midpoint - 0.001, 0.0).map(|p| p.0), Some(line), "{}x{} line {}", size, size, line);
+                assert_eq!(resolve(midpoint + 0.001, 0.0).map(|p| p.0), Some(line + 1), "{}x{} line {}", size, size, line);
+                assert_eq!(resolve(0.0, midpoint - 0.001).map(|p| p.1), Some(line), "{}x{} line {}", size, size, line);
+                assert_eq!(resolve(0.0, midpoint + 0.001).map(|p| p.1), Some(line + 1), "{}x{} line {}", size, size, line);
+            }
+        }
+
+        // An exact midpoint (representable on 9x9) goes to the higher-numbered line
+        let game = game(9);
+        assert_eq!(game.click_to_intersection(-0.875, 0.875), Some((1, 8)));
+        assert_eq!(game.click_to_intersection(0.125, -0.125), Some((5, 4)));
+    }
+
+
+    // Micro-benchmark of the legality checks and capture detection behind playouts: random
+    // 19x19 games checking every point before each move. Run it optimized with
+    // `cargo test --release playout_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn playout_benchmark() {
+        let mut best = std::time::Duration::MAX;
+        for _ in 0..5 {
+            let mut rng = Rng::new(12345);
+            let started = std::time::Instant::now();
+            let mut moves = 0;
+            for _ in 0..30 {
+                let mut game = game(19);
+                for _ in 0..400 {
+                    let mut legal = Vec::new();
+                    for y in 0..19 {
+                        for x in 0..19 {
+                            if game.check_move(x, y, game.current_player) == MoveResult::Success {
+                                legal.push((x, y));
+                            }
+                        }
+                    }
+                    if legal.is_empty() {
+                        break;
+                    }
+                    let (x, y) = legal[rng.below(legal.len())];
+                    assert_eq!(game.play_move(x, y), MoveResult::Success);
+                    moves += 1;
+                }
+                game.goto_move(0);
+                game.redo_all();
+            }
+            best = best.min(started.elapsed());
+            assert!(moves > 0);
+        }
+        println!("30 playouts of up to 400 moves: best of 5 {:?}", best);
+    }
+
+
+    // Place stones on a bare Board, asserting each is legal
+    fn place_all(board: &mut Board, stones: &[(usize, usize, StoneState)]) {
+        for &(x, y, color) in stones {
+            assert_eq!(board.place(x, y, color).map(|captured| captured.len()), Ok(0), "{:?} at ({}, {})", color, x, y);
+        }
+    }
+
+    #[test]
+    fn board_places_stones_and_rejects_occupied_points() {
+        let mut board = Board::new(9);
+        assert_eq!(board.size(), 9);
+        assert_eq!(board.place(4, 4, StoneState::Black), Ok(Vec::new()));
+        assert_eq!(board.stone(4, 4), StoneState::Black);
+        assert_eq!(board.stone(9, 0), StoneState::Empty);
+        assert_eq!(board.place(4, 4, StoneState::White), Err(MoveResult::Occupied));
+        assert_eq!(board.place(9, 4, StoneState::White), Err(MoveResult::OutOfBounds));
+        assert_eq!(board.place(3, 3, StoneState::Empty), Err(MoveResult::InvalidColor));
+        assert_eq!(board.is_legal(3, 3, StoneState::White), Ok(()));
+    }
+
+    #[test]
+    fn board_captures_groups_without_liberties() {
+        use StoneState::{Black, White};
+        let mut board = Board::new(9);
+        // Two white stones on the edge, surrounded except for (2, 0)
+        place_all(&mut board, &[(0, 0, White), (1, 0, White), (0, 1, Black), (1, 1, Black)]);
+        let mut captured = board.place(2, 0, Black).unwrap();
+        captured.sort();
+        assert_eq!(captured, vec![(0, 0), (1, 0)]);
+        assert_eq!(board.stone(0, 0), StoneState::Empty);
+        assert_eq!(board.stone(1, 0), StoneState::Empty);
+        // A capture of two stones leaves no ko
+        assert_eq!(board.ko(), None);
+
+        // capture only removes groups that really have no liberties
+        assert!(board.capture(2, 0).is_empty());
+        assert_eq!(board.stone(2, 0), Black);
+    }
+
+    #[test]
+    fn board_rejects_suicide_unless_it_captures() {
+        use StoneState::{Black, White};
+        let mut board = Board::new(9);
+        place_all(&mut board, &[(1, 0, Black), (0, 1, Black)]);
+        assert_eq!(board.is_legal(0, 0, White), Err(MoveResult::Suicide));
+        assert_eq!(board.place(0, 0, White), Err(MoveResult::Suicide));
+        assert_eq!(board.stone(0, 0), StoneState::Empty);
+        // Filling its own eye is legal while the group keeps another liberty
+        assert_eq!(board.is_legal(0, 0, Black), Ok(()));
+
+        // With the black stones in atari, the same point captures instead
+        place_all(&mut board, &[(2, 0, White), (1, 1, White), (0, 2, White)]);
+        let mut captured = board.place(0, 0, White).unwrap();
+        captured.sort();
+        assert_eq!(captured, vec![(0, 1), (1, 0)]);
+    }
+
+    #[test]
+    fn board_enforces_simple_ko() {
+        use StoneState::{Black, White};
+        let mut board = Board::new(9);
+        // Black (2, 1) has one liberty at (1, 1), which White's ko shape surrounds
+        place_all(&mut board, &[
+            (1, 0, Black), (0, 1, Black), (1, 2, Black),
+            (2, 0, White), (3, 1, White), (2, 2, White), (2, 1, Black),
+        ]);
+        assert_eq!(board.place(1, 1, White), Ok(vec![(2, 1)]));
+        assert_eq!(board.ko(), Some((2, 1, Black)));
+        assert_eq!(board.is_legal(2, 1, Black), Err(MoveResult::Ko));
+        assert_eq!(board.place(2, 1, Black), Err(MoveResult::Ko));
+
+        // Any other move lifts the ko
+        place_all(&mut board, &[(6, 6, Black)]);
+        assert_eq!(board.ko(), None);
+        assert_eq!(board.place(2, 1, Black), Ok(vec![(1, 1)]));
+    }
+
+    #[test]
+    fn board_area_score_counts_stones_and_surrounded_regions() {
+        use StoneState::{Black, White};
+        let mut board = Board::new(7);
+        assert_eq!(board.score(6.5), -6.5);
+
+        // A black wall on column 2 and a white wall on column 4: columns 0-1 are Black's,
+        // 5-6 White's and column 3 is neutral
+        for y in 0..7 {
+            place_all(&mut board, &[(2, y, Black), (4, y, White)]);
+        }
+        assert_eq!(board.score(0.0), 0.0);
+        place_all(&mut board, &[(3, 0, Black)]);
+        assert_eq!(board.score(0.5), 0.5);
+    }
+
+
+    #[test]
+    fn verify_sequence_replays_under_the_game_rules() {
+        // Retaking a ko immediately is only legal without a ko rule
+        let mut no_ko = game(9);
+        assert!(no_ko.set_ko_rule(KoRule::None as u8));
+        play(&mut no_ko, &[(1, 0), (2, 0), (0, 1), (3, 1), (1, 2), (2, 2), (2, 1), (1, 1), (2, 1)]);
+        assert_eq!(no_ko.verify_sequence(), "consistent");
+
+        // Free handicap: Black's opening stones in a row
+        let mut handicap = game(9);
+        assert!(handicap.place_free_handicap(2, false));
+        play(&mut handicap, &[(2, 2), (6, 6), (4, 4)]);
+        assert_eq!(handicap.verify_sequence(), "consistent");
+
+        // Moves inside a play area
+        let mut area = game(9);
+        assert!(area.set_play_area(0, 0, 4, 4));
+        play(&mut area, &[(1, 1), (3, 3), (2, 2)]);
+        assert_eq!(area.verify_sequence(), "consistent");
+
+        // Under superko, a replay doesn't trip the repetition limit
+        let mut superko = game(9);
+        assert!(superko.set_ko_rule(KoRule::PositionalSuperko as u8));
+        play(&mut superko, &[(4, 4), (3, 3)]);
+        superko.handle_pass();
+        superko.handle_pass();
+        assert_eq!(superko.verify_sequence(), "consistent");
+    }
+
+
+    #[test]
+    fn fill_dame_returns_to_the_scoring_phase() {
+        let mut game = game(9);
+        // Black walls off columns 0-2 and White columns 6-8, leaving column 4 as dame; a
+        // white stone inside Black's area is marked dead
+        for y in 0..9 {
+            play(&mut game, &[(3, y), (5, y)]);
+        }
+        play(&mut game, &[(8, 8), (1, 1)]);
+        game.handle_pass();
+        game.handle_pass();
+        assert!(game.toggle_dead_group(1, 1));
+        let dead = game.dead_stones.clone();
+
+        let filled = game.fill_dame();
+        assert!(filled > 0);
+        assert_eq!(game.game_phase(), GamePhase::Scoring);
+        assert_eq!(game.dead_stones, dead);
+        assert_eq!(game.get_consecutive_passes(), 2);
+
+        // A second call is accepted; nothing safe is left to fill
+        assert_eq!(game.fill_dame(), 0);
+        assert_eq!(game.game_phase(), GamePhase::Scoring);
+        assert_eq!(game.dead_stones, dead);
+    }
+
+
+    #[test]
+    fn capturing_race_verdicts_agree_with_semeai() {
+        use StoneState::{Black, White};
+        let mut game = game(9);
+        // Black (0, 1)-(1, 1) has two outside liberties, one of which White can only fill
+        // after an approach move; White (0, 2)-(1, 2) has one
+        for (x, y, color) in [(0, 1, Black), (1, 1, Black), (0, 2, White), (1, 2, White), (2, 1, White), (2, 2, Black), (1, 3, Black)] {
+            game.board[y][x] = color;
+        }
+
+        for to_move in [Black, White] {
+            game.current_player = to_move;
+            let result = game.semeai_result(0, 1, 0, 2, to_move as u8).unwrap();
+            let analysis = game.analyze_capturing_race(0, 1, 0, 2).unwrap();
+            assert!(result.contains("\"approach\":1"), "{}", result);
+            assert!(result.contains("\"winner\":\"first\""), "{}", result);
+            assert!(analysis.contains("\"verdict\":\"first\""), "{}", analysis);
+            assert!(analysis.contains("\"first\":{\"color\":\"black\",\"liberties\":2,\"exclusive\":2,\"eyes\":0}"), "{}", analysis);
+            assert_eq!(game.semeai_winner(0, 1, 0, 2), 1);
+        }
+
+        assert_eq!(game.semeai_result(0, 1, 0, 2, 0), None);
+        assert_eq!(game.analyze_capturing_race(0, 1, 1, 1), None);
+    }
+
+    #[test]
+    fn autoplay_steps_to_the_end_and_manual_navigation_pauses_it() {
+        let mut g = game(9);
+        play(&mut g, &[(2, 2), (6, 6), (2, 6), (6, 2)]);
+        g.goto_move(0);
+
+        assert!(g.start_autoplay(100));
+        assert!(g.autoplay_tick(0.0)); // starts the clock
+        assert!(g.autoplay_tick(50.0));
+        assert_eq!(g.get_move_index(), 0);
+        assert!(g.autoplay_tick(100.0));
+        assert_eq!(g.get_move_index(), 1);
+        for (step, now) in [(2, 200.0), (3, 300.0)] {
+            assert!(g.autoplay_tick(now));
+            assert_eq!(g.get_move_index(), step);
+        }
+        assert!(!g.autoplay_tick(400.0));
+        assert_eq!(g.get_move_index(), 4);
+        assert!(!g.is_autoplaying());
+        assert!(!g.start_autoplay(100));
+
+        let navigations: [fn(&mut GoGame); 5] = [
+            |g| { g.undo(); },
+            |g| { g.redo(); },
+            |g| { g.goto_move(3); },
+            |g| { g.undo_n(1); },
+            |g| { g.redo_n(0); },
+        ];
+        for navigate in navigations {
+            g.goto_move(1);
+            assert!(g.start_autoplay(100));
+            navigate(&mut g);
+            assert!(!g.is_autoplaying());
+            let index = g.get_move_index();
+            assert!(!g.autoplay_tick(1000.0));
+            assert_eq!(g.get_move_index(), index);
+        }
+    }
+
+
+    #[test]
+    fn handle_click_plays_through_the_move_rules() {
+        let mut g = game(9);
+        assert_eq!(g.handle_click(-1.0, -1.0), MoveResult::Success);
+        assert_eq!(g.move_sequence.len(), 1);
+        assert_eq!(g.current_player, StoneState::White);
+        assert_eq!(g.handle_click(-1.0, -1.0), MoveResult::Occupied);
+        assert_eq!(g.handle_click(2.0, 0.0), MoveResult::OutOfBounds);
+
+        // White captures the corner stone; undo restores it
+        assert_eq!(g.handle_click(-0.75, -1.0), MoveResult::Success);
+        assert_eq!(g.handle_click(0.0, 0.0), MoveResult::Success);
+        assert_eq!(g.handle_click(-1.0, -0.75), MoveResult::Success);
+        assert_eq!(g.board[0][0], StoneState::Empty);
+        assert_eq!(g.move_sequence.last().unwrap().captured.len(), 1);
+        assert!(g.undo());
+        assert_eq!(g.board[0][0], StoneState::Black);
+    }
+
+
+    #[test]
+    fn pass_moves_report_real_colors_in_one_color_mode() {
+        let mut g = game(9);
+        g.set_one_color_mode(true);
+        play(&mut g, &[(2, 2)]);
+        g.handle_pass();
+        play(&mut g, &[(6, 6), (3, 3)]);
+        g.handle_pass();
+        assert_eq!(&*g.get_pass_moves(), &[2, 1]);
+    }
+
+
+    #[test]
+    fn event_log_is_shared_by_every_game() {
+        let mut first = game(9);
+        let mut second = game(9);
+        GoGame::clear_event_log();
+        first.play_move(2, 2);
+        second.play_move(6, 6);
+
+        let log = GoGame::drain_log();
+        assert!(log.contains("at (2, 2)") && log.contains("at (6, 6)"), "{}", log);
+        assert_eq!(GoGame::drain_log(), "");
+        assert!(!GoGame::is_verbose());
+    }
+
+
+    #[test]
+    fn disputed_points_count_dame_and_atari_regions() {
+        let mut g = game(9);
+        assert_eq!(g.disputed_points(), 0);
+
+        // Walls with an open column between them: only that column is dame
+        let mut stones = Vec::new();
+        for y in 0..9 {
+            stones.push((3, y, StoneState::Black));
+            stones.push((5, y, StoneState::White));
+        }
+        place_all(&mut g.board, &stones);
+        assert_eq!(g.disputed_points(), 9);
+
+        // A black group whose last liberty is a point only Black borders
+        let mut g = game(9);
+        place_all(
+            &mut g.board,
+            &[
+                (1, 0, StoneState::Black),
+                (0, 1, StoneState::Black),
+                (1, 1, StoneState::Black),
+                (2, 0, StoneState::White),
+                (2, 1, StoneState::White),
+                (1, 2, StoneState::White),
+                (0, 2, StoneState::White),
+            ],
+        );
+        assert_eq!(g.disputed_points(), 1);
+    }
+
+
+    #[test]
+    fn peek_board_size_reads_without_a_game() {
+        for size in [9, 13, 19] {
+            let mut g = game(size);
+            play(&mut g, &[(2, 2)]);
+            assert_eq!(GoGame::peek_board_size(&g.serialize_state()), size as i32);
+        }
+        assert_eq!(GoGame::peek_board_size("not base64!"), -1);
+        assert_eq!(GoGame::peek_board_size(""), -1);
+    }
+
+
+    #[test]
+    fn captures_at_cover_undone_moves() {
+        let mut g = game(9);
+        play(&mut g, &[(0, 0), (1, 0), (4, 4), (0, 1)]);
+        let captured = vec![Point { x: 0, y: 0 }];
+        assert_eq!(g.get_captures_at(3), captured);
+
+        g.undo_n(2);
+        assert_eq!(g.get_captures_at(3), captured);
+        assert_eq!(g.get_captures_at(3), g.get_captures_at_move(3));
+        assert!(g.get_captures_at(2).is_empty());
+        assert!(g.get_captures_at(4).is_empty());
+    }
+
+
+    #[test]
+    fn board_edits_clear_move_markers_and_ko() {
+        // Black takes a ko at (2, 1)
+        let mut g = game(9);
+        play(&mut g, &[(2, 0), (1, 0), (3, 1), (0, 1), (2, 2), (1, 2), (8, 8), (2, 1), (1, 1)]);
+        assert_eq!(g.board.ko, Some((2, 1, StoneState::White)));
+        assert_eq!(g.move_numbers[1][1], 9);
+        assert_eq!(g.last_action, LastAction::Stone(1, 1));
+
+        assert_eq!(g.set_board_position(1, 1, 2), MoveResult::Success);
+        assert_eq!(g.board[1][1], StoneState::White);
+        assert_eq!(g.move_numbers[1][1], 0);
+        assert_eq!(g.last_action, LastAction::None);
+        assert_eq!(g.board.ko, None);
+        assert_eq!(g.setup_board[1][1], StoneState::Empty);
+
+        // With no moves played the edit is part of the setup position
+        let mut g = game(9);
+        assert_eq!(g.set_board_position(3, 3, 1), MoveResult::Success);
+        assert_eq!(g.setup_board[3][3], StoneState::Black);
+        play(&mut g, &[(5, 5)]);
+        g.undo_all();
+        assert_eq!(g.board[3][3], StoneState::Black);
+        assert_eq!(g.move_numbers[3][3], 0);
+    }
+
+
+    #[test]
+    fn peek_next_move_shows_passes_and_ends_with_none() {
+        let mut g = game(9);
+        assert_eq!(g.peek_next_move(), None);
+
+        play(&mut g, &[(0, 0), (1, 0), (4, 4), (0, 1)]);
+        g.handle_pass();
+        g.undo_n(2);
+
+        let capture = g.peek_next_move().unwrap();
+        assert_eq!((capture.x, capture.y, capture.player, capture.is_pass), (0, 1, 2, false));
+        assert_eq!((capture.move_number, capture.captured_count), (4, 1));
+        assert_eq!(g.get_move_index(), 3);
+
+        g.redo();
+        let pass = g.peek_next_move().unwrap();
+        assert_eq!((pass.x, pass.y, pass.player, pass.is_pass), (-1, -1, 1, true));
+        assert_eq!((pass.move_number, pass.captured_count), (5, 0));
+
+        g.redo();
+        assert_eq!(g.peek_next_move(), None);
+    }
+
+
+    #[test]
+    fn territory_regions_outline_an_l_shaped_corner() {
+        // Black encloses (0, 0), (1, 0) and (0, 1); a white stone makes the rest dame
+        let mut g = game(9);
+        place_all(
+            &mut g.board,
+            &[(2, 0, StoneState::Black), (1, 1, StoneState::Black), (0, 2, StoneState::Black), (8, 8, StoneState::White)],
+        );
+        // One closed loop, half a line inside the black stones and along the edges
+        assert_eq!(
+            g.get_territory_regions(),
+            concat!(
+                "[{\"owner\":1,\"size\":3,\"segments\":[",
+                "[0,1.5,0,0.5],[0,0.5,0,0],[0,0,0.5,0],[0.5,0,1.5,0],",
+                "[1.5,0,1.5,0.5],[1.5,0.5,0.5,0.5],[0.5,0.5,0.5,1.5],[0.5,1.5,0,1.5]]}]"
+            )
+        );
+    }
+
+
+    #[test]
+    fn even_match_alternates_colors_until_decided() {
+        let mut g = game(9);
+        g.set_komi(6.5);
+        assert!(!g.start_match(0, HandicapRule::Even));
+        assert!(g.start_match(3, HandicapRule::Even));
+        assert!(!g.next_game()); // no result yet
+
+        // Player one wins game one as Black, then loses game two as Black (player two's game)
+        assert!(g.record_result(1));
+        assert!(!g.record_result(1));
+        assert!(g.next_game());
+        assert!(g.record_result(1));
+        assert!(g.next_game());
+        assert_eq!(
+            g.get_match_score().unwrap(),
+            "{\"games\":3,\"results\":[1,2],\"player_one\":1,\"player_two\":1,\"current_game\":3,\"black\":1,\"handicap\":0,\"komi\":6.5,\"finished\":false}"
+        );
+
+        // Player two takes game three with White and the match
+        assert!(g.record_result(2));
+        assert!(g.get_match_score().unwrap().ends_with("\"player_one\":1,\"player_two\":2,\"current_game\":3,\"black\":1,\"handicap\":0,\"komi\":6.5,\"finished\":true}"));
+        assert!(!g.next_game());
+        assert!(!g.record_result(1));
+    }
+
+    #[test]
+    fn loser_gains_stone_match_resumes_from_a_saved_state() {
+        let mut g = game(9);
+        g.set_komi(6.5);
+        assert!(g.start_match(5, HandicapRule::LoserGainsStone));
+
+        // Player two loses game one and takes Black without komi
+        assert!(g.record_result(1));
+        assert!(g.next_game());
+        assert!(g.get_match_score().unwrap().contains("\"black\":2,\"handicap\":0,\"komi\":0.5"));
+
+        // Player two loses again (as Black) and receives two stones
+        assert!(g.record_result(2));
+        assert!(g.next_game());
+        assert!(g.get_match_score().unwrap().contains("\"current_game\":3,\"black\":2,\"handicap\":2,\"komi\":0.5"));
+        assert_eq!(g.board.stones.iter().flatten().filter(|&&stone| stone == StoneState::Black).count(), 2);
+        assert_eq!(g.current_player, StoneState::White);
+        play(&mut g, &[(4, 4)]);
+
+        // Save mid-game and resume the match in another game
+        let mut resumed = game(9);
+        assert!(resumed.deserialize_state(&g.serialize_state()));
+        assert_eq!(resumed.get_match_score(), g.get_match_score());
+        assert_eq!(resumed.get_komi(), 0.5);
+
+        // Player two wins with the handicap and goes back to Black without komi
+        assert!(resumed.record_result(1));
+        assert!(resumed.next_game());
+        assert_eq!(
+            resumed.get_match_score().unwrap(),
+            "{\"games\":5,\"results\":[1,1,2],\"player_one\":2,\"player_two\":1,\"current_game\":4,\"black\":2,\"handicap\":0,\"komi\":0.5,\"finished\":false}"
+        );
+        assert!(resumed.move_sequence.is_empty());
+    }
+
+
+    #[test]
+    fn game_over_reasons_map_to_results() {
+        // Capture go: White's capture decides the game
+        let mut g = game(9);
+        assert!(g.set_variant("capture_go"));
+        play(&mut g, &[(0, 0), (1, 0), (4, 4)]);
+        assert_eq!((g.get_game_over_reason(), g.get_result()), (None, None));
+        play(&mut g, &[(0, 1)]);
+        assert_eq!(g.game_over_reason(), Some(GameOverReason::CaptureGo));
+        assert_eq!(g.get_game_over_reason().as_deref(), Some("Capture Go"));
+        assert_eq!(g.get_result().as_deref(), Some("W+Capture"));
+        assert!(g.is_game_over());
+
+        // Capture go ended by two passes has no winner
+        let mut g = game(9);
+        g.set_variant("capture_go");
+        g.handle_pass();
+        g.handle_pass();
+        assert_eq!(g.get_game_over_reason().as_deref(), Some("Void/No result"));
+        assert_eq!(g.get_result().as_deref(), Some("Void"));
+
+        // An accepted score ends the game without a decided result
+        let mut g = game(9);
+        g.set_pass_accepts_score(true);
+        g.handle_pass();
+        g.handle_pass();
+        assert!(!g.is_game_over());
+        assert_eq!(g.handle_pass(), "Score accepted");
+        assert_eq!(g.get_game_over_reason().as_deref(), Some("Score accepted"));
+        assert_eq!(g.get_result(), None);
+    }
+
+
+    #[test]
+    fn annotations_of_undone_moves_are_not_serialized() {
+        let mut g = game(9);
+        play(&mut g, &[(2, 2), (6, 6), (4, 4)]);
+        assert!(g.set_move_quality(0, MoveQuality::Good));
+        assert!(g.set_current_move_quality(2));
+        g.undo_n(1);
+
+        let mut loaded = game(9);
+        assert!(loaded.deserialize_state(&g.serialize_state()));
+        assert_eq!(loaded.move_sequence.len(), 2);
+        assert_eq!(loaded.get_move_quality(0), MoveQuality::Good);
+        assert_eq!(loaded.get_move_quality(1), MoveQuality::None);
+    }
+
+
+    #[test]
+    fn loading_a_game_drops_discarded_branches() {
+        let mut other = game(9);
+        play(&mut other, &[(4, 4)]);
+        let state = other.serialize_state();
+
+        for load in [
+            (|g: &mut GoGame, state: &str| g.deserialize_state(state)) as fn(&mut GoGame, &str) -> bool,
+            |g, _| g.import_sgf("(;GM[1]SZ[9];B[cc];W[gg])"),
+        ] {
+            let mut g = game(9);
+            g.set_preserve_branches(true);
+            play(&mut g, &[(2, 2), (6, 6)]);
+            g.undo();
+            play(&mut g, &[(6, 2)]);
+            assert_ne!(g.list_discarded_branches(), "[]");
+
+            assert!(load(&mut g, &state));
+            assert_eq!(g.list_discarded_branches(), "[]");
+        }
+    }
+
+
+    #[test]
+    fn free_placement_turn_changes_are_recorded() {
+        let mut g = game(9);
+        play(&mut g, &[(2, 2)]);
+        g.set_free_placement(false);
+        assert_eq!(g.current_player, StoneState::White);
+
+        g.set_free_placement(true);
+        assert_eq!(g.current_player, StoneState::Black);
+        assert_eq!(g.check_invariants(), "OK");
+        assert!(g.set_placement_color(2));
+        assert_eq!(g.current_player, StoneState::White);
+        assert_eq!(g.check_invariants(), "OK");
+
+        g.undo();
+        g.redo();
+        assert_eq!(g.current_player, StoneState::White);
+        let mut loaded = game(9);
+        assert!(loaded.deserialize_state(&g.serialize_state()));
+        assert_eq!(loaded.current_player, StoneState::White);
+    }
+
+
+    #[test]
+    fn manual_free_handicap_survives_saving_and_becomes_setup() {
+        let mut g = game(9);
+        assert!(g.place_free_handicap(3, false));
+        play(&mut g, &[(2, 2)]);
+
+        // Saved mid-placement, Black still has two stones to place after loading
+        let mut loaded = game(9);
+        assert!(loaded.deserialize_state(&g.serialize_state()));
+        assert_eq!(loaded.get_free_handicap_remaining(), 2);
+        assert_eq!(loaded.current_player, StoneState::Black);
+        play(&mut loaded, &[(6, 6)]);
+        assert_eq!(loaded.current_player, StoneState::Black);
+
+        // The last stone turns the handicap into setup stones with White to move
+        play(&mut loaded, &[(4, 4)]);
+        assert_eq!(loaded.get_free_handicap_remaining(), 0);
+        assert_eq!(loaded.current_player, StoneState::White);
+        assert!(!loaded.can_undo());
+        assert_eq!(loaded.setup_board[4][4], StoneState::Black);
+        assert_eq!(loaded.setup_player, StoneState::White);
+
+        play(&mut loaded, &[(3, 3)]);
+        loaded.undo_n(5);
+        assert_eq!(loaded.board.stones.iter().flatten().filter(|&&stone| stone == StoneState::Black).count(), 3);
+        assert_eq!(loaded.current_player, StoneState::White);
+        assert_eq!(loaded.check_invariants(), "OK");
+    }
+
+
+    #[test]
+    fn setup_snapshot_hides_colors_in_one_color_mode() {
+        let mut g = game(9);
+        assert_eq!(g.set_board_position(2, 2, 1), MoveResult::Success);
+        assert_eq!(g.set_board_position(6, 6, 2), MoveResult::Success);
+        let at = |snapshot: &[u8], x: usize, y: usize| snapshot[y * 9 + x];
+
+        let snapshot = g.get_setup_snapshot();
+        assert_eq!((at(&snapshot, 2, 2), at(&snapshot, 6, 6)), (1, 2));
+
+        g.set_one_color_mode(true);
+        let snapshot = g.get_setup_snapshot();
+        assert_eq!((at(&snapshot, 2, 2), at(&snapshot, 6, 6)), (1, 1));
+        assert_eq!(snapshot, g.get_board_flat());
+
+        g.set_reveal(true);
+        assert_eq!(at(&g.get_setup_snapshot(), 6, 6), 2);
+    }
+}